@@ -1,19 +1,27 @@
 use crate::{
-    flashbots::{BundleRequest, FlashbotsMiddleware, PendingBundleError},
+    flashbots::{
+        BundleRequest, FlashbotsMiddleware, MevShareBundle, PendingBundle, PendingBundleError,
+        RelaySubmission, SimulatedBundle, SimulatedMevBundle, SimulationDifference,
+    },
     Credentials,
 };
 use ethers::prelude::*;
 use log::*;
 use rand::{distributions::Alphanumeric, Rng};
 use reqwest::{RequestBuilder, Response};
-use shared::config::Config as NftyConfig;
+use shared::config::{Config as NftyConfig, SimulationMode};
 use std::{io::Cursor, sync::Arc, time::Duration};
+use uuid::Uuid;
 
 pub trait StaticMiddleware = 'static + Middleware;
 pub trait StaticSigner = 'static + Signer + Clone;
 
 pub static SOUND_FILE: &[u8] = include_bytes!("../assets/success.mp3");
 
+/// How many blocks a private transaction stays valid for when no bundle
+/// window is configured.
+pub const DEFAULT_PRIVATE_TX_BLOCKS: u64 = 25;
+
 #[derive(Clone)]
 pub struct Context<M, S> {
     session_id: String,
@@ -22,12 +30,14 @@ pub struct Context<M, S> {
     http: reqwest::Client,
     autosolve: Option<autosolve::Client>,
     provider: Arc<SignerMiddleware<FlashbotsMiddleware<M, S>, S>>,
+    pubsub: Provider<Ws>,
 }
 
 impl<M: StaticMiddleware, S: StaticSigner> Context<M, S> {
     pub async fn new(
         config: NftyConfig,
         provider: SignerMiddleware<FlashbotsMiddleware<M, S>, S>,
+        pubsub: Provider<Ws>,
         autosolve: Option<autosolve::Client>,
         credentials: Credentials,
         session_id: String,
@@ -41,6 +51,7 @@ impl<M: StaticMiddleware, S: StaticSigner> Context<M, S> {
             http,
             autosolve,
             provider: Arc::new(provider),
+            pubsub,
         })
     }
 
@@ -60,6 +71,11 @@ impl<M: StaticMiddleware, S: StaticSigner> Context<M, S> {
         &self.provider
     }
 
+    /// The websocket provider, for subscriptions.
+    pub fn pubsub(&self) -> &Provider<Ws> {
+        &self.pubsub
+    }
+
     pub fn http(&self) -> &reqwest::Client {
         &self.http
     }
@@ -130,7 +146,128 @@ impl<M: StaticMiddleware, S: StaticSigner> Context<M, S> {
         }
     }
 
-    pub async fn send_bundle(&self, bundle: &BundleRequest) -> Result<(), shared::Error> {
+    pub fn log_relay_health(&self) {
+        for (relay, health) in self.provider().inner().relay_health() {
+            info!(
+                "relay {}: {} requests, {:.1}% errors ({} client, {} request), avg {}ms, {} inclusions{}",
+                relay,
+                health.requests,
+                health.error_rate() * 100.0,
+                health.client_errors,
+                health.request_errors,
+                health.average_latency().unwrap_or_default().as_millis(),
+                health.inclusions,
+                if health.is_cooling_down() {
+                    ", cooling down"
+                } else {
+                    ""
+                }
+            );
+        }
+    }
+
+    fn log_submissions(kind: &str, submissions: &[RelaySubmission]) {
+        for submission in submissions {
+            match submission.error.as_ref() {
+                None => debug!(
+                    "relay {} accepted {} in {}ms",
+                    submission.relay,
+                    kind,
+                    submission.latency.as_millis()
+                ),
+                Some(e) => warn!(
+                    "relay {} rejected {} in {}ms: {}",
+                    submission.relay,
+                    kind,
+                    submission.latency.as_millis(),
+                    e
+                ),
+            }
+        }
+    }
+
+    pub async fn simulate_bundle(
+        &self,
+        bundle: &BundleRequest,
+    ) -> Result<SimulatedBundle, shared::Error> {
+        let flashbots = self.provider().inner();
+        let result = match self.config.global.simulation_mode {
+            Some(SimulationMode::Local) => {
+                return Ok(flashbots.simulate_bundle_locally(bundle).await?)
+            }
+            Some(SimulationMode::Compare) => self.compare_simulations(bundle).await,
+            _ => flashbots.simulate_bundle(bundle).await.map_err(Into::into),
+        };
+
+        match result {
+            Ok(simulated_bundle) => Ok(simulated_bundle),
+            Err(e) => {
+                warn!("relay simulation failed ({}), simulating locally...", e);
+                Ok(flashbots.simulate_bundle_locally(bundle).await?)
+            }
+        }
+    }
+
+    async fn compare_simulations(
+        &self,
+        bundle: &BundleRequest,
+    ) -> Result<SimulatedBundle, shared::Error> {
+        let comparison = self
+            .provider()
+            .inner()
+            .simulate_bundle_on_all(bundle)
+            .await?;
+        for simulation in &comparison.simulations {
+            match simulation.result.as_ref() {
+                Ok(simulated_bundle) => info!(
+                    "simulation on {} ({}ms): coinbase_diff={} effective_gas_price={}",
+                    simulation.relay,
+                    simulation.latency.as_millis(),
+                    simulated_bundle.coinbase_diff,
+                    simulated_bundle.effective_gas_price().as_u128() as f64 / 1e9
+                ),
+                Err(e) => warn!(
+                    "simulation on {} failed ({}ms): {}",
+                    simulation.relay,
+                    simulation.latency.as_millis(),
+                    e
+                ),
+            }
+        }
+
+        for difference in comparison.differences() {
+            match difference {
+                SimulationDifference::CoinbaseDiff {
+                    relay,
+                    expected,
+                    actual,
+                } => warn!(
+                    "simulation mismatch on {}: coinbase_diff {} != {}",
+                    relay, actual, expected
+                ),
+                SimulationDifference::TransactionError {
+                    relay,
+                    transaction,
+                    expected,
+                    actual,
+                } => warn!(
+                    "simulation mismatch on {}: tx 0x{:x} error {:?} != {:?}",
+                    relay, transaction, actual, expected
+                ),
+            }
+        }
+
+        match comparison.baseline() {
+            Some(baseline) => Ok(baseline.result.clone()?),
+            None => Err("bundle simulation failed on every relay".into()),
+        }
+    }
+
+    pub async fn send_bundle(
+        &self,
+        bundle: &BundleRequest,
+        blocks: u64,
+    ) -> Result<(), shared::Error> {
         #[cfg(feature = "themida")]
         unsafe {
             crate::themida::VM_DOLPHIN_BLACK_START()
@@ -146,21 +283,218 @@ impl<M: StaticMiddleware, S: StaticSigner> Context<M, S> {
         }
 
         info!("Sending bundle...");
-        let pending_bundle = self.provider().inner().send_bundle(bundle).await?;
+        let pending_bundle = self
+            .provider()
+            .inner()
+            .send_bundle_range(bundle, blocks)
+            .await?;
+
+        let bundle_hash = pending_bundle.bundle_hash();
+        let last_block = pending_bundle.last_block();
+        match self.wait_for_bundle(pending_bundle, blocks).await {
+            Err(PendingBundleError::BundleNotIncluded) => {
+                // the post-mortem is slow, so it must not hold up the next attempt
+                let ctx = self.clone();
+                let bundle = bundle.clone();
+                tokio::spawn(async move {
+                    match ctx
+                        .provider()
+                        .inner()
+                        .explain_miss(&bundle, bundle_hash, last_block)
+                        .await
+                    {
+                        Ok(reason) => warn!(
+                            "Bundle 0x{:x} missed block {}: {}",
+                            bundle_hash, last_block, reason
+                        ),
+                        Err(e) => warn!("error explaining bundle miss: {}", e),
+                    }
+                });
+                Err(PendingBundleError::BundleNotIncluded.into())
+            }
+            result => Ok(result?),
+        }
+    }
+
+    pub async fn simulate_mev_bundle(
+        &self,
+        bundle: &MevShareBundle,
+    ) -> Result<SimulatedMevBundle, shared::Error> {
+        let simulated_bundle = self
+            .provider()
+            .inner()
+            .simulate_mev_bundle(bundle, None)
+            .await?;
+        if !simulated_bundle.success {
+            return Err(format!(
+                "MEV-Share bundle simulation failed: {}",
+                simulated_bundle.error.as_deref().unwrap_or("unknown error")
+            )
+            .into());
+        }
+        Ok(simulated_bundle)
+    }
+
+    pub async fn send_mev_bundle(&self, bundle: &MevShareBundle) -> Result<(), shared::Error> {
+        #[cfg(feature = "themida")]
+        unsafe {
+            crate::themida::VM_DOLPHIN_BLACK_START()
+        }
+
+        if *crate::HAS_AUTHED.lock().await != 6969 {
+            std::process::exit(-1);
+        }
+
+        #[cfg(feature = "themida")]
+        unsafe {
+            crate::themida::VM_DOLPHIN_BLACK_END()
+        }
+
+        info!("Sending MEV-Share bundle...");
+        let pending_bundle = self.provider().inner().send_mev_bundle(bundle).await?;
+
+        let blocks = match (bundle.block(), bundle.max_block()) {
+            (Some(block), Some(max_block)) => (max_block - block).as_u64() + 1,
+            _ => 1,
+        };
+        Ok(self.wait_for_bundle(pending_bundle, blocks).await?)
+    }
+
+    async fn wait_for_bundle<P: JsonRpcClient>(
+        &self,
+        pending_bundle: PendingBundle<'_, P>,
+        blocks: u64,
+    ) -> Result<(), PendingBundleError> {
+        let submissions = pending_bundle.submissions().to_vec();
+        Self::log_submissions("bundle", &submissions);
+        info!(
+            "Bundle 0x{:x} accepted by {}/{} relays",
+            pending_bundle.bundle_hash(),
+            submissions.iter().filter(|s| s.is_ok()).count(),
+            submissions.len()
+        );
+
         match pending_bundle.await {
-            Ok(_) => {
-                info!("Bundle included!");
+            Ok(included) => {
+                match included.confirmation_delay {
+                    Some(delay) => info!(
+                        "Bundle included in block {}! (confirmed {}ms after block arrival)",
+                        included.block,
+                        delay.as_millis()
+                    ),
+                    None => info!("Bundle included in block {}!", included.block),
+                }
+                self.provider().inner().record_inclusion(&submissions);
                 tokio::spawn(Self::play_success_sound());
                 Ok(())
             }
             Err(PendingBundleError::BundleNotIncluded) => {
-                warn!("Bundle was not included in target block!");
-                Err(PendingBundleError::BundleNotIncluded.into())
+                warn!(
+                    "Bundle was not included in target block{}!",
+                    if blocks > 1 { "s" } else { "" }
+                );
+                Err(PendingBundleError::BundleNotIncluded)
             }
             Err(e) => {
                 error!("Error sending bundle: {}", e);
-                Err(e.into())
+                Err(e)
             }
         }
     }
+
+    pub async fn cancel_bundle(&self, replacement_uuid: Uuid) -> Result<(), shared::Error> {
+        info!("Cancelling bundle {}...", replacement_uuid);
+        let submissions = self
+            .provider()
+            .inner()
+            .cancel_bundle(replacement_uuid)
+            .await?;
+        for submission in submissions.iter().filter(|s| !s.is_ok()) {
+            warn!(
+                "relay {} failed to cancel bundle: {}",
+                submission.relay,
+                submission.error.as_deref().unwrap_or_default()
+            );
+        }
+        Ok(())
+    }
+
+    pub async fn send_private_transaction(
+        &self,
+        tx: Bytes,
+        max_block_number: U64,
+    ) -> Result<TxHash, shared::Error> {
+        info!("Sending private transaction...");
+        let private_tx = self
+            .provider()
+            .inner()
+            .send_private_transaction(tx, Some(max_block_number))
+            .await?;
+
+        Self::log_submissions("private transaction", &private_tx.submissions);
+        info!(
+            "Private transaction 0x{:x} accepted by {}/{} relays",
+            private_tx.hash,
+            private_tx.submissions.iter().filter(|s| s.is_ok()).count(),
+            private_tx.submissions.len()
+        );
+        Ok(private_tx.hash)
+    }
+
+    /// Waits until every private transaction is included or `max_block_number`
+    /// has passed, and returns the transactions that were not included.
+    pub async fn wait_for_private_transactions(
+        &self,
+        tx_hashes: &[TxHash],
+        max_block_number: U64,
+    ) -> Result<Vec<TxHash>, shared::Error> {
+        let mut pending = tx_hashes.to_vec();
+        loop {
+            let mut still_pending = Vec::with_capacity(pending.len());
+            for tx_hash in pending {
+                match self.provider().get_transaction_receipt(tx_hash).await? {
+                    Some(receipt) => info!(
+                        "Private transaction 0x{:x} included in block {}!",
+                        tx_hash,
+                        receipt.block_number.unwrap_or_default()
+                    ),
+                    None => still_pending.push(tx_hash),
+                }
+            }
+            pending = still_pending;
+
+            if pending.is_empty() {
+                tokio::spawn(Self::play_success_sound());
+                return Ok(pending);
+            }
+
+            if self.provider().get_block_number().await? >= max_block_number {
+                warn!(
+                    "{} private transaction(s) were not included before block {}!",
+                    pending.len(),
+                    max_block_number
+                );
+                return Ok(pending);
+            }
+
+            tokio::time::sleep(self.provider().provider().get_interval()).await;
+        }
+    }
+
+    pub async fn cancel_private_transaction(&self, tx_hash: TxHash) -> Result<(), shared::Error> {
+        info!("Cancelling private transaction 0x{:x}...", tx_hash);
+        let submissions = self
+            .provider()
+            .inner()
+            .cancel_private_transaction(tx_hash)
+            .await?;
+        for submission in submissions.iter().filter(|s| !s.is_ok()) {
+            warn!(
+                "relay {} failed to cancel private transaction: {}",
+                submission.relay,
+                submission.error.as_deref().unwrap_or_default()
+            );
+        }
+        Ok(())
+    }
 }
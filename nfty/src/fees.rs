@@ -0,0 +1,364 @@
+use ethers::prelude::*;
+use log::*;
+use serde::{Deserialize, Serialize};
+use shared::config::{Config as NftyConfig, FeeStrategy as FeeStrategyConfig, SmartGas};
+
+/// How many blocks of fee history the base fee is predicted from, by default.
+const DEFAULT_HISTORY_BLOCKS: u64 = 10;
+/// The percentile of recent priority fees paid, by default, when a strategy
+/// has no fixed priority fee.
+const DEFAULT_PRIORITY_FEE_PERCENTILE: f64 = 50.;
+/// The maximum change of the base fee from one block to the next, as a
+/// fraction of the base fee.
+const BASE_FEE_MAX_CHANGE: f64 = 0.125;
+
+/// The fees of an EIP-1559 transaction.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Fees {
+    pub max_fee_per_gas: U256,
+    pub max_priority_fee_per_gas: U256,
+}
+
+impl Fees {
+    /// Fees that pay everything above the base fee to the builder.
+    pub fn flat(fee: U256) -> Self {
+        Self {
+            max_fee_per_gas: fee,
+            max_priority_fee_per_gas: fee,
+        }
+    }
+
+    /// Raises the max fee to `max_fee` if it is lower, keeping the priority
+    /// fee.
+    pub fn raise_to(self, max_fee: U256) -> Self {
+        let max_fee_per_gas = self.max_fee_per_gas.max(max_fee);
+        Self {
+            max_fee_per_gas,
+            max_priority_fee_per_gas: self.max_priority_fee_per_gas.min(max_fee_per_gas),
+        }
+    }
+
+    /// The fees with the smart gas of a task, which spends up to `budget`
+    /// per gas: enabled smart gas raises the max fee to it, and exclusive
+    /// smart gas pays all of it as the priority fee.
+    pub fn with_smart_gas(self, smart_gas: &SmartGas, budget: U256) -> Self {
+        match smart_gas {
+            SmartGas::Enabled => self.raise_to(budget),
+            SmartGas::Disabled => self,
+            SmartGas::Exclusive => Self::flat(budget),
+        }
+    }
+}
+
+/// How the fees of the transactions of a task are chosen.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FeeStrategy {
+    /// The gas fee and priority fee of the task config.
+    Fixed(Fees),
+    /// Fees that follow the base fee, predicted from the fee history of
+    /// recent blocks.
+    Dynamic {
+        base_fee_multiplier: f64,
+        priority_fee: Option<U256>,
+        priority_fee_percentile: f64,
+        maximum_fee: U256,
+        history_blocks: u64,
+    },
+}
+
+// `Serialize` is required by `Provider::request`.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FeeHistory {
+    /// The base fee of every block of the history, followed by the base fee
+    /// of the next block.
+    base_fee_per_gas: Vec<U256>,
+    gas_used_ratio: Vec<f64>,
+    #[serde(default)]
+    reward: Vec<Vec<U256>>,
+}
+
+impl FeeStrategy {
+    /// The strategy named by a task, or the fixed gas fee and priority fee
+    /// (in gwei) of the task if it does not name one.
+    pub fn for_task(
+        config: &NftyConfig,
+        name: Option<&String>,
+        gas_fee: f64,
+        priority_fee: Option<f64>,
+    ) -> Result<Self, shared::Error> {
+        let name = match name.filter(|n| !n.is_empty()) {
+            Some(name) => name,
+            None => {
+                let gas_fee = gwei(gas_fee);
+                return Ok(FeeStrategy::Fixed(Fees {
+                    max_fee_per_gas: gas_fee,
+                    max_priority_fee_per_gas: priority_fee.map(gwei).unwrap_or(gas_fee),
+                }));
+            }
+        };
+
+        config
+            .global
+            .fee_strategies
+            .iter()
+            .flatten()
+            .find(|strategy| &strategy.name == name)
+            .map(Self::from)
+            .ok_or_else(|| format!("unknown fee strategy: {}", name).into())
+    }
+
+    /// The fees of transactions that may be included up to `blocks` blocks
+    /// from now.
+    pub async fn fees<M: Middleware>(
+        &self,
+        provider: &M,
+        blocks: u64,
+    ) -> Result<Fees, shared::Error> {
+        let (priority_fee_percentile, history_blocks) = match self {
+            FeeStrategy::Fixed(fees) => return Ok(*fees),
+            FeeStrategy::Dynamic {
+                priority_fee_percentile,
+                history_blocks,
+                ..
+            } => (*priority_fee_percentile, *history_blocks),
+        };
+
+        let history = fee_history(provider, history_blocks, &[priority_fee_percentile]).await?;
+        let fees = self.compute(&history, blocks);
+        debug!(
+            "fees: max {} gwei, priority {} gwei",
+            fees.max_fee_per_gas.as_u128() as f64 / 1e9,
+            fees.max_priority_fee_per_gas.as_u128() as f64 / 1e9
+        );
+        Ok(fees)
+    }
+
+    fn compute(&self, history: &FeeHistory, blocks: u64) -> Fees {
+        let (base_fee_multiplier, priority_fee, maximum_fee) = match self {
+            FeeStrategy::Fixed(fees) => return *fees,
+            FeeStrategy::Dynamic {
+                base_fee_multiplier,
+                priority_fee,
+                maximum_fee,
+                ..
+            } => (*base_fee_multiplier, *priority_fee, *maximum_fee),
+        };
+
+        let base_fee = predict_base_fee(history, blocks);
+        let priority_fee = priority_fee
+            .unwrap_or_else(|| median_reward(history))
+            .min(maximum_fee);
+        let max_fee =
+            U256::from((base_fee.as_u128() as f64 * base_fee_multiplier) as u128) + priority_fee;
+
+        Fees {
+            max_fee_per_gas: max_fee.min(maximum_fee),
+            max_priority_fee_per_gas: priority_fee,
+        }
+    }
+}
+
+impl From<&FeeStrategyConfig> for FeeStrategy {
+    fn from(config: &FeeStrategyConfig) -> Self {
+        FeeStrategy::Dynamic {
+            base_fee_multiplier: config.base_fee_multiplier.max(1.),
+            priority_fee: config.priority_fee.map(gwei),
+            priority_fee_percentile: config
+                .priority_fee_percentile
+                .unwrap_or(DEFAULT_PRIORITY_FEE_PERCENTILE)
+                .clamp(0., 100.),
+            maximum_fee: gwei(config.maximum_fee),
+            history_blocks: config.history_blocks.unwrap_or(DEFAULT_HISTORY_BLOCKS),
+        }
+    }
+}
+
+/// Predicts the base fee `blocks` blocks from now, whatever the strategy of
+/// the task.
+pub async fn base_fee<M: Middleware>(provider: &M, blocks: u64) -> Result<U256, shared::Error> {
+    let history = fee_history(provider, DEFAULT_HISTORY_BLOCKS, &[]).await?;
+    Ok(predict_base_fee(&history, blocks))
+}
+
+async fn fee_history<M: Middleware>(
+    provider: &M,
+    blocks: u64,
+    reward_percentiles: &[f64],
+) -> Result<FeeHistory, shared::Error> {
+    Ok(provider
+        .provider()
+        .request(
+            "eth_feeHistory",
+            (
+                U256::from(blocks.max(1)),
+                BlockNumber::Latest,
+                reward_percentiles,
+            ),
+        )
+        .await?)
+}
+
+fn gwei(fee: f64) -> U256 {
+    U256::from((fee * 1e9) as u128)
+}
+
+/// Predicts the base fee `blocks` blocks from now.
+///
+/// The base fee of the next block is known. The blocks after it are expected
+/// to be as full as the average block of the history, which moves the base
+/// fee by up to 12.5% per block.
+fn predict_base_fee(history: &FeeHistory, blocks: u64) -> U256 {
+    let next_base_fee = history.base_fee_per_gas.last().cloned().unwrap_or_default();
+    if history.gas_used_ratio.is_empty() {
+        return next_base_fee;
+    }
+
+    let gas_used_ratio =
+        history.gas_used_ratio.iter().sum::<f64>() / history.gas_used_ratio.len() as f64;
+    let change = 1. + BASE_FEE_MAX_CHANGE * (gas_used_ratio - 0.5) / 0.5;
+    let mut base_fee = next_base_fee.as_u128() as f64;
+    for _ in 1..blocks.max(1) {
+        base_fee *= change;
+    }
+    U256::from(base_fee.ceil() as u128)
+}
+
+/// The median of the priority fee percentile paid in the blocks of the
+/// history, ignoring empty blocks.
+fn median_reward(history: &FeeHistory) -> U256 {
+    let mut rewards = history
+        .reward
+        .iter()
+        .filter_map(|reward| reward.first().cloned())
+        .filter(|reward| !reward.is_zero())
+        .collect::<Vec<_>>();
+    rewards.sort();
+    rewards.get(rewards.len() / 2).cloned().unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn history(base_fee: f64, gas_used_ratio: &[f64], reward: &[f64]) -> FeeHistory {
+        FeeHistory {
+            base_fee_per_gas: vec![gwei(base_fee); gas_used_ratio.len() + 1],
+            gas_used_ratio: gas_used_ratio.to_vec(),
+            reward: reward.iter().map(|reward| vec![gwei(*reward)]).collect(),
+        }
+    }
+
+    fn strategy(priority_fee: Option<f64>, maximum_fee: f64) -> FeeStrategy {
+        FeeStrategy::Dynamic {
+            base_fee_multiplier: 2.,
+            priority_fee: priority_fee.map(gwei),
+            priority_fee_percentile: DEFAULT_PRIORITY_FEE_PERCENTILE,
+            maximum_fee: gwei(maximum_fee),
+            history_blocks: DEFAULT_HISTORY_BLOCKS,
+        }
+    }
+
+    #[test]
+    fn fees_predict_base_fee() {
+        // the base fee of the next block is known
+        let full = history(100., &[1., 1.], &[]);
+        assert_eq!(predict_base_fee(&full, 0), gwei(100.));
+        assert_eq!(predict_base_fee(&full, 1), gwei(100.));
+        // full blocks raise it by 12.5% per block after the next one
+        assert_eq!(predict_base_fee(&full, 2), gwei(112.5));
+        assert_eq!(predict_base_fee(&full, 3), gwei(126.5625));
+
+        // empty blocks lower it by 12.5% per block
+        let empty = history(100., &[0., 0.], &[]);
+        assert_eq!(predict_base_fee(&empty, 3), gwei(76.5625));
+
+        // half full blocks keep it
+        let half_full = history(100., &[0.25, 0.75], &[]);
+        assert_eq!(predict_base_fee(&half_full, 5), gwei(100.));
+
+        // without gas used ratios only the next base fee is known
+        let unknown = history(100., &[], &[]);
+        assert_eq!(predict_base_fee(&unknown, 5), gwei(100.));
+    }
+
+    #[test]
+    fn fees_median_reward() {
+        assert_eq!(median_reward(&history(100., &[], &[])), U256::zero());
+        // empty blocks pay no reward and are ignored
+        assert_eq!(median_reward(&history(100., &[], &[0., 0.])), U256::zero());
+        assert_eq!(
+            median_reward(&history(100., &[], &[0., 3., 1., 0., 2.])),
+            gwei(2.)
+        );
+    }
+
+    #[test]
+    fn fees_compute_maximum_fee() {
+        let history = history(100., &[0.5], &[1., 3., 2.]);
+
+        // 2 × 100 gwei + the median reward
+        assert_eq!(
+            strategy(None, 500.).compute(&history, 1),
+            Fees {
+                max_fee_per_gas: gwei(202.),
+                max_priority_fee_per_gas: gwei(2.),
+            }
+        );
+        // the max fee is capped
+        assert_eq!(
+            strategy(Some(5.), 150.).compute(&history, 1),
+            Fees {
+                max_fee_per_gas: gwei(150.),
+                max_priority_fee_per_gas: gwei(5.),
+            }
+        );
+        // and so is the priority fee
+        assert_eq!(
+            strategy(Some(200.), 150.).compute(&history, 1),
+            Fees {
+                max_fee_per_gas: gwei(150.),
+                max_priority_fee_per_gas: gwei(150.),
+            }
+        );
+    }
+
+    #[test]
+    fn fees_raise_to() {
+        let fees = Fees {
+            max_fee_per_gas: gwei(10.),
+            max_priority_fee_per_gas: gwei(2.),
+        };
+        assert_eq!(
+            fees.raise_to(gwei(15.)),
+            Fees {
+                max_fee_per_gas: gwei(15.),
+                max_priority_fee_per_gas: gwei(2.),
+            }
+        );
+        // fees are never lowered
+        assert_eq!(fees.raise_to(gwei(5.)), fees);
+    }
+
+    #[test]
+    fn fees_with_smart_gas() {
+        let fees = Fees {
+            max_fee_per_gas: gwei(10.),
+            max_priority_fee_per_gas: gwei(2.),
+        };
+        // the configured priority fee is kept
+        assert_eq!(
+            fees.with_smart_gas(&SmartGas::Enabled, gwei(15.)),
+            Fees {
+                max_fee_per_gas: gwei(15.),
+                max_priority_fee_per_gas: gwei(2.),
+            }
+        );
+        assert_eq!(fees.with_smart_gas(&SmartGas::Enabled, gwei(5.)), fees);
+        assert_eq!(fees.with_smart_gas(&SmartGas::Disabled, gwei(15.)), fees);
+        assert_eq!(
+            fees.with_smart_gas(&SmartGas::Exclusive, gwei(15.)),
+            Fees::flat(gwei(15.))
+        );
+    }
+}
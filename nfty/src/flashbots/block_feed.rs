@@ -0,0 +1,53 @@
+use ethers_core::types::U64;
+use ethers_providers::{Middleware, Provider, PubsubClient};
+use futures_util::stream::StreamExt;
+use std::time::Instant;
+use tokio::sync::watch;
+
+/// A block received from a block subscription.
+#[derive(Debug, Clone, Copy)]
+pub struct BlockArrival {
+    /// The number of the block.
+    pub number: U64,
+    /// When the block was received.
+    pub received_at: Instant,
+}
+
+/// A feed of the latest block received from a block subscription.
+///
+/// See [`spawn_block_feed`].
+pub type BlockFeed = watch::Receiver<Option<BlockArrival>>;
+
+/// Subscribes to new blocks on a pubsub provider in the background, and
+/// publishes every new block to the returned feed.
+///
+/// The feed is closed if the subscription fails or ends, so consumers
+/// should fall back to polling when that happens.
+pub fn spawn_block_feed<P: PubsubClient + 'static>(provider: Provider<P>) -> BlockFeed {
+    let (tx, rx) = watch::channel(None);
+
+    tokio::spawn(async move {
+        let mut blocks = match provider.subscribe_blocks().await {
+            Ok(blocks) => blocks,
+            Err(_) => return,
+        };
+
+        while let Some(block) = blocks.next().await {
+            let number = match block.number {
+                Some(number) => number,
+                None => continue,
+            };
+
+            let arrival = BlockArrival {
+                number,
+                received_at: Instant::now(),
+            };
+            if tx.send(Some(arrival)).is_err() {
+                // every consumer is gone
+                break;
+            }
+        }
+    });
+
+    rx
+}
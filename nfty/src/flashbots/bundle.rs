@@ -1,10 +1,15 @@
 use crate::flashbots::utils::{deserialize_optional_h160, deserialize_u256, deserialize_u64};
 use chrono::{DateTime, Utc};
 use ethers_core::{
-    types::{transaction::response::Transaction, Address, Bytes, TxHash, H256, U256, U64},
+    types::{
+        transaction::{eip2718::TypedTransaction, response::Transaction},
+        Address, Bytes, Eip1559TransactionRequest, TxHash, H256, U256, U64,
+    },
     utils::keccak256,
 };
+use ethers_signers::Signer;
 use serde::{Deserialize, Serialize, Serializer};
+use uuid::Uuid;
 
 /// A bundle hash.
 pub type BundleHash = H256;
@@ -30,6 +35,24 @@ impl From<Bytes> for BundleTransaction {
     }
 }
 
+/// The gas limit of coinbase payment transactions, which call a payment
+/// contract that forwards the payment.
+pub const COINBASE_PAYMENT_GAS: u64 = 50_000;
+
+/// How a bundle pays the builder of its block directly, on top of the
+/// priority fees of its transactions: by calling a payment contract with the
+/// payment as value, which forwards it to `block.coinbase`.
+///
+/// The same bundle is sent to every relay, so the payment must go to
+/// whichever builder includes it rather than to a fixed recipient.
+///
+/// See [`BundleRequest::push_coinbase_payment`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct CoinbasePayment {
+    pub address: Address,
+    pub data: Bytes,
+}
+
 /// A bundle that can be submitted to a Flashbots relay.
 ///
 /// The bundle can include your own transactions and transactions from
@@ -65,6 +88,9 @@ pub struct BundleRequest {
     #[serde(skip_serializing_if = "Option::is_none")]
     max_timestamp: Option<u64>,
 
+    #[serde(skip_serializing_if = "Option::is_none")]
+    replacement_uuid: Option<Uuid>,
+
     #[serde(rename = "stateBlockNumber")]
     #[serde(skip_serializing_if = "Option::is_none")]
     simulation_block: Option<U64>,
@@ -122,11 +148,47 @@ impl BundleRequest {
         self
     }
 
+    /// Signs a transaction paying `value` to the builder of the block with
+    /// `signer`, and adds it to the end of the bundle request.
+    ///
+    /// Since a bundle is included as a whole or not at all, the payment is
+    /// only made if the preceding transactions succeed. The payment
+    /// transaction does not pay a priority fee, but it still pays the base
+    /// fee, which must be below `max_fee_per_gas`.
+    pub async fn push_coinbase_payment<S: Signer>(
+        &mut self,
+        signer: &S,
+        payment: &CoinbasePayment,
+        value: U256,
+        nonce: U256,
+        max_fee_per_gas: U256,
+    ) -> Result<&mut Self, S::Error> {
+        let tx = TypedTransaction::Eip1559(
+            Eip1559TransactionRequest::new()
+                .from(signer.address())
+                .to(payment.address)
+                .value(value)
+                .data(payment.data.clone())
+                .nonce(nonce)
+                .gas(COINBASE_PAYMENT_GAS)
+                .max_fee_per_gas(max_fee_per_gas)
+                .max_priority_fee_per_gas(0),
+        );
+        let signature = signer.sign_transaction(&tx).await?;
+
+        Ok(self.push_transaction(tx.rlp_signed(signer.chain_id(), &signature)))
+    }
+
     /// Get a reference to the transactions currently in the bundle request.
     pub fn transactions(&self) -> &Vec<BundleTransaction> {
         &self.transactions
     }
 
+    /// Get the hashes of the transactions that are allowed to revert.
+    pub fn revertible_transaction_hashes(&self) -> &Vec<H256> {
+        &self.revertible_transaction_hashes
+    }
+
     /// Get a list of transaction hashes in the bundle request.
     pub fn transaction_hashes(&self) -> Vec<TxHash> {
         self.transactions
@@ -206,6 +268,42 @@ impl BundleRequest {
         self.max_timestamp = Some(timestamp);
         self
     }
+
+    /// Get the replacement UUID of the bundle (if any).
+    pub fn replacement_uuid(&self) -> Option<Uuid> {
+        self.replacement_uuid
+    }
+
+    /// Set the replacement UUID of the bundle.
+    ///
+    /// Sending a bundle with the same replacement UUID as a previously sent
+    /// bundle replaces the previous bundle at the relay. The UUID can also
+    /// be used to cancel the bundle, see
+    /// [`FlashbotsMiddleware::cancel_bundle`](crate::flashbots::FlashbotsMiddleware::cancel_bundle).
+    pub fn set_replacement_uuid(&mut self, uuid: Uuid) -> &mut Self {
+        self.replacement_uuid = Some(uuid);
+        self
+    }
+
+    /// A copy of the bundle that targets `block`, with a replacement UUID of
+    /// its own derived from the replacement UUID of the bundle (if any).
+    ///
+    /// Relays keep a single bundle per replacement UUID, so the bundles of a
+    /// range of blocks need different UUIDs not to replace each other. The
+    /// derived UUID only depends on the block, so a rebuilt bundle for the
+    /// same block still replaces the previous one.
+    pub(crate) fn for_block(&self, block: U64) -> Self {
+        let mut bundle = self.clone();
+        bundle.block = Some(block);
+        bundle.replacement_uuid = self.replacement_uuid.map(|uuid| {
+            let mut bytes = *uuid.as_bytes();
+            for (byte, block_byte) in bytes[8..].iter_mut().zip(block.as_u64().to_be_bytes()) {
+                *byte ^= block_byte;
+            }
+            Uuid::from_bytes(bytes)
+        });
+        bundle
+    }
 }
 
 /// Details of a simulated transaction.
@@ -336,11 +434,77 @@ pub struct BundleStats {
     pub sent_to_miners_at: Option<DateTime<Utc>>,
 }
 
+/// Represents stats for a submitted bundle, as returned by
+/// `flashbots_getBundleStatsV2`.
+///
+/// See [Flashbots docs][fb_getbundlestatsv2] for more information.
+///
+/// [fb_getbundlestatsv2]: https://docs.flashbots.net/flashbots-auction/searchers/advanced/rpc-endpoint/#flashbots_getbundlestatsv2
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BundleStatsV2 {
+    /// Whether the bundle is high priority.
+    pub is_high_priority: bool,
+    /// Whether the bundle was simulated.
+    pub is_simulated: bool,
+    /// When the bundle was simulated
+    pub simulated_at: Option<DateTime<Utc>>,
+    /// When the bundle was received by the relay
+    pub received_at: Option<DateTime<Utc>>,
+    /// The builders that considered the bundle, and when
+    #[serde(default)]
+    pub considered_by_builders_at: Vec<BuilderTimestamp>,
+    /// The builders that sealed a block with the bundle, and when
+    #[serde(default)]
+    pub sealed_by_builders_at: Vec<BuilderTimestamp>,
+}
+
+/// When a builder acted on a bundle.
+#[derive(Deserialize)]
+pub struct BuilderTimestamp {
+    /// The public key of the builder.
+    pub pubkey: String,
+    /// When the builder acted on the bundle.
+    pub timestamp: DateTime<Utc>,
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::flashbots::transaction::decode_raw_transaction;
+    use ethers_signers::LocalWallet;
     use std::str::FromStr;
 
+    #[tokio::test]
+    async fn bundle_coinbase_payment() {
+        let wallet = "380eb0f3d505f087e438eca80bc4df9a7faa24f868e69fc0440261a0fc0567dc"
+            .parse::<LocalWallet>()
+            .unwrap()
+            .with_chain_id(1u64);
+        let payment = CoinbasePayment {
+            address: Address::repeat_byte(0x11),
+            data: vec![0x1b, 0x92, 0x65, 0xb8].into(),
+        };
+
+        let mut bundle = BundleRequest::new();
+        bundle
+            .push_transaction(Bytes::from(vec![0x1]))
+            .push_coinbase_payment(&wallet, &payment, 1000.into(), 8.into(), 100.into())
+            .await
+            .unwrap();
+
+        let raw = match &bundle.transactions()[1] {
+            BundleTransaction::Raw(raw) => raw.clone(),
+            BundleTransaction::Signed(_) => unreachable!(),
+        };
+        let tx = decode_raw_transaction(&raw).unwrap();
+        assert_eq!(tx.from, wallet.address());
+        assert_eq!(tx.to, Some(Address::repeat_byte(0x11)));
+        assert_eq!(tx.value, U256::from(1000));
+        assert_eq!(tx.nonce, U256::from(8));
+        assert_eq!(tx.priority_fee(90.into()), U256::zero());
+    }
+
     #[test]
     fn bundle_serialize() {
         let mut bundle = BundleRequest::new();
@@ -360,6 +524,42 @@ mod tests {
         );
     }
 
+    #[test]
+    fn bundle_serialize_replacement_uuid() {
+        let mut bundle = BundleRequest::new();
+
+        bundle
+            .push_transaction(Bytes::from(vec![0x1]))
+            .set_block(2.into())
+            .set_replacement_uuid(Uuid::parse_str("d9e0a0b6-2b4c-4b5e-9a43-1e5c0a6e3f2a").unwrap());
+
+        assert_eq!(
+            &serde_json::to_string(&bundle).unwrap(),
+            r#"{"txs":["0x01"],"blockNumber":"0x2","replacementUuid":"d9e0a0b6-2b4c-4b5e-9a43-1e5c0a6e3f2a"}"#
+        );
+    }
+
+    #[test]
+    fn bundle_for_block_replacement_uuid() {
+        let mut bundle = BundleRequest::new();
+        bundle
+            .set_block(2.into())
+            .set_replacement_uuid(Uuid::parse_str("d9e0a0b6-2b4c-4b5e-9a43-1e5c0a6e3f2a").unwrap());
+
+        let first = bundle.for_block(2.into());
+        let second = bundle.for_block(3.into());
+        assert_eq!(second.block(), Some(3.into()));
+        assert_ne!(first.replacement_uuid(), second.replacement_uuid());
+        assert_eq!(
+            first.replacement_uuid(),
+            bundle.for_block(2.into()).replacement_uuid()
+        );
+        assert_eq!(
+            BundleRequest::new().for_block(2.into()).replacement_uuid(),
+            None
+        );
+    }
+
     #[test]
     fn simulated_bundle_deserialize() {
         let simulated_bundle: SimulatedBundle = serde_json::from_str(
@@ -0,0 +1,172 @@
+use std::{
+    cmp::Ordering,
+    time::{Duration, Instant},
+};
+
+/// How many transport failures in a row put a relay in cool-down.
+pub const FAILURE_THRESHOLD: u32 = 3;
+
+/// How long a relay in cool-down is skipped, before it is probed again.
+pub const COOL_DOWN: Duration = Duration::from_secs(60);
+
+/// The kind of failure a relay request ended with.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum RelayFailure {
+    /// The relay rejected the request with a 4xx status code.
+    ///
+    /// This is usually caused by the request itself, so it does not count
+    /// towards the cool-down.
+    Client,
+    /// The request failed, or the relay responded with a 5xx status code.
+    Request,
+    /// The relay responded with a JSON-RPC error.
+    JsonRpc,
+    /// The response of the relay could not be deserialized.
+    Response,
+    /// The request could not be serialized or signed.
+    Local,
+}
+
+/// The health of a relay, as seen by the requests sent to it.
+#[derive(Debug, Clone, Default)]
+pub struct RelayHealth {
+    /// The number of requests sent to the relay.
+    pub requests: u64,
+    /// The number of requests the relay rejected with a 4xx status code.
+    pub client_errors: u64,
+    /// The number of requests that failed, or that the relay responded to
+    /// with a 5xx status code.
+    pub request_errors: u64,
+    /// The number of requests that failed in any other way.
+    pub other_errors: u64,
+    /// The number of bundles accepted by the relay that were included.
+    pub inclusions: u64,
+    /// The total time spent waiting for the relay.
+    pub total_latency: Duration,
+    /// The number of transport failures since the last success.
+    pub consecutive_failures: u32,
+    /// When the relay will be probed again, if it is in cool-down.
+    pub cool_down_until: Option<Instant>,
+}
+
+impl RelayHealth {
+    /// The number of failed requests.
+    pub fn errors(&self) -> u64 {
+        self.client_errors + self.request_errors + self.other_errors
+    }
+
+    /// The share of requests that failed, between 0 and 1.
+    pub fn error_rate(&self) -> f64 {
+        if self.requests == 0 {
+            0.0
+        } else {
+            self.errors() as f64 / self.requests as f64
+        }
+    }
+
+    /// The average time the relay took to respond (if it was sent anything).
+    pub fn average_latency(&self) -> Option<Duration> {
+        if self.requests == 0 {
+            None
+        } else {
+            Some(self.total_latency / self.requests as u32)
+        }
+    }
+
+    /// Whether the relay is skipped because it kept failing.
+    pub fn is_cooling_down(&self) -> bool {
+        self.cool_down_until
+            .map(|until| until > Instant::now())
+            .unwrap_or(false)
+    }
+
+    pub(crate) fn record(&mut self, latency: Duration, failure: Option<RelayFailure>) {
+        self.requests += 1;
+        self.total_latency += latency;
+
+        let failure = match failure {
+            Some(failure) => failure,
+            None => {
+                self.consecutive_failures = 0;
+                self.cool_down_until = None;
+                return;
+            }
+        };
+
+        match failure {
+            RelayFailure::Client => self.client_errors += 1,
+            RelayFailure::Request => self.request_errors += 1,
+            _ => self.other_errors += 1,
+        }
+
+        if matches!(failure, RelayFailure::Request | RelayFailure::Response) {
+            self.consecutive_failures += 1;
+            // A relay that is probed after its cool-down and fails again goes
+            // straight back into cool-down.
+            if self.consecutive_failures >= FAILURE_THRESHOLD {
+                self.cool_down_until = Some(Instant::now() + COOL_DOWN);
+            }
+        }
+    }
+
+    /// Orders relays from the most to the least reliable: the lowest error
+    /// rate first, then the most inclusions, then the lowest latency.
+    pub(crate) fn compare(&self, other: &Self) -> Ordering {
+        self.error_rate()
+            .partial_cmp(&other.error_rate())
+            .unwrap_or(Ordering::Equal)
+            .then_with(|| other.inclusions.cmp(&self.inclusions))
+            .then_with(|| {
+                self.average_latency()
+                    .unwrap_or_default()
+                    .cmp(&other.average_latency().unwrap_or_default())
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn relay_cool_down() {
+        let mut health = RelayHealth::default();
+
+        // client errors are caused by the request, not the relay
+        for _ in 0..FAILURE_THRESHOLD {
+            health.record(Duration::from_millis(10), Some(RelayFailure::Client));
+        }
+        assert!(!health.is_cooling_down());
+
+        for _ in 0..FAILURE_THRESHOLD {
+            health.record(Duration::from_millis(10), Some(RelayFailure::Request));
+        }
+        assert!(health.is_cooling_down());
+        assert_eq!(health.errors(), 2 * FAILURE_THRESHOLD as u64);
+        assert_eq!(health.average_latency(), Some(Duration::from_millis(10)));
+
+        health.record(Duration::from_millis(10), None);
+        assert!(!health.is_cooling_down());
+        assert_eq!(health.consecutive_failures, 0);
+    }
+
+    #[test]
+    fn relay_ordering() {
+        let mut reliable = RelayHealth::default();
+        reliable.record(Duration::from_millis(100), None);
+        reliable.inclusions = 1;
+
+        let mut fast = RelayHealth::default();
+        fast.record(Duration::from_millis(10), None);
+
+        let mut failing = RelayHealth::default();
+        failing.record(Duration::from_millis(1), Some(RelayFailure::Request));
+
+        let mut relays = vec![failing, fast, reliable];
+        relays.sort_by(|a, b| a.compare(b));
+
+        assert_eq!(relays[0].inclusions, 1);
+        assert_eq!(relays[1].average_latency(), Some(Duration::from_millis(10)));
+        assert_eq!(relays[2].request_errors, 1);
+    }
+}
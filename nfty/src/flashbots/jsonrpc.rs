@@ -4,7 +4,7 @@
 use ethers_core::types::U256;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use std::fmt;
+use std::{collections::HashMap, fmt};
 use thiserror::Error;
 
 #[derive(Serialize, Deserialize, Debug, Clone, Error)]
@@ -66,6 +66,11 @@ impl<'a, T> Request<'a, T> {
             params,
         }
     }
+
+    /// The id of the request
+    pub fn id(&self) -> u64 {
+        self.id
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -93,6 +98,31 @@ impl<R> ResponseData<R> {
     }
 }
 
+/// Matches the responses to a batch request back to the ids of its requests.
+///
+/// The responses of a batch can come back in any order, and requests without
+/// a response get an error.
+pub fn match_batch_responses<R>(
+    ids: &[u64],
+    responses: Vec<Response<R>>,
+) -> Vec<Result<R, JsonRpcError>> {
+    let mut responses = responses
+        .into_iter()
+        .map(|res| (res.id, res.data))
+        .collect::<HashMap<_, _>>();
+
+    ids.iter()
+        .map(|id| match responses.remove(id) {
+            Some(data) => data.into_result(),
+            None => Err(JsonRpcError {
+                code: -32603,
+                message: format!("Missing response for request {}", id),
+                data: None,
+            }),
+        })
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -105,6 +135,37 @@ mod tests {
         assert_eq!(response.data.into_result().unwrap(), 19);
     }
 
+    #[test]
+    fn match_batch() {
+        let responses: Vec<Response<u64>> = serde_json::from_str(
+            r#"[
+                {"jsonrpc": "2.0", "error": {"code": -32000, "message": "failed"}, "id": 3},
+                {"jsonrpc": "2.0", "result": 19, "id": 1}
+            ]"#,
+        )
+        .unwrap();
+
+        let results = match_batch_responses(&[1, 2, 3], responses);
+        assert_eq!(results[0].as_ref().unwrap(), &19);
+        assert_eq!(results[1].as_ref().unwrap_err().code, -32603);
+        assert_eq!(results[2].as_ref().unwrap_err().message, "failed");
+    }
+
+    #[test]
+    fn match_batch_reversed() {
+        let responses: Vec<Response<u64>> = serde_json::from_str(
+            r#"[
+                {"jsonrpc": "2.0", "result": 8, "id": 8},
+                {"jsonrpc": "2.0", "result": 7, "id": 7}
+            ]"#,
+        )
+        .unwrap();
+
+        let results = match_batch_responses(&[7, 8], responses);
+        assert_eq!(results[0].as_ref().unwrap(), &7);
+        assert_eq!(results[1].as_ref().unwrap(), &8);
+    }
+
     #[test]
     fn ser_request() {
         let request: Request<()> = Request::new(300, "method_name", ());
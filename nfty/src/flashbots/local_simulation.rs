@@ -0,0 +1,308 @@
+use crate::flashbots::{
+    bundle::{BundleRequest, BundleTransaction, SimulatedBundle, SimulatedTransaction},
+    transaction::{decode_raw_transaction, DecodedTransaction},
+};
+use ethers_core::{
+    types::{Address, Bytes, U256, U64},
+    utils::keccak256,
+};
+use ethers_providers::{JsonRpcClient, Provider, ProviderError};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use thiserror::Error;
+
+/// Errors for local bundle simulations.
+#[derive(Error, Debug)]
+pub enum LocalSimulationError {
+    /// A transaction of the bundle could not be decoded.
+    #[error("Could not decode transaction {0} of the bundle")]
+    InvalidTransaction(usize),
+    /// The node did not trace every transaction of the bundle.
+    #[error("The node traced {0} transaction(s) of the bundle")]
+    MissingTraces(usize),
+    /// The response could not be deserialized.
+    #[error(transparent)]
+    SerdeJson(#[from] serde_json::Error),
+    /// An error occured while interacting with the RPC endpoint.
+    #[error(transparent)]
+    ProviderError(#[from] ProviderError),
+}
+
+// `Serialize` is required by `Provider::request`.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BlockHeader {
+    base_fee_per_gas: Option<U256>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CallTrace {
+    gas_used: U256,
+    error: Option<String>,
+    revert_reason: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct CallRequest {
+    from: Address,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    to: Option<Address>,
+    gas: U256,
+    value: U256,
+    data: Bytes,
+}
+
+#[derive(Debug, Serialize)]
+struct TraceConfig {
+    tracer: &'static str,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct TraceBundle {
+    transactions: Vec<CallRequest>,
+    block_override: BlockOverride,
+}
+
+#[derive(Debug, Serialize)]
+struct BlockOverride {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    number: Option<U64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    time: Option<U64>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SimulationContext {
+    block_number: U64,
+    // the end of the block
+    transaction_index: i64,
+}
+
+/// Simulate a bundle against a node instead of a relay.
+///
+/// Nodes that support `eth_callBundle` simulate the bundle in one go.
+/// Otherwise the transactions are traced in order with `debug_traceCallMany`
+/// on top of the simulation block and at the simulation timestamp, so every
+/// transaction sees the state changes of the ones before it. Coinbase
+/// transfers are not included in the coinbase difference in that case.
+///
+/// The bundle must have a simulation block.
+pub(crate) async fn simulate_bundle<P: JsonRpcClient>(
+    provider: &Provider<P>,
+    bundle: &BundleRequest,
+) -> Result<SimulatedBundle, LocalSimulationError> {
+    match provider
+        .request::<_, Value>("eth_callBundle", [bundle])
+        .await
+    {
+        Ok(simulated_bundle) => return Ok(serde_json::from_value(simulated_bundle)?),
+        Err(e) if !is_unsupported(&e) => return Err(e.into()),
+        Err(_) => {}
+    }
+
+    let simulation_block = bundle.simulation_block().unwrap_or_default();
+    let header: BlockHeader = provider
+        .request("eth_getBlockByNumber", (simulation_block, false))
+        .await?;
+    let base_fee = header.base_fee_per_gas.unwrap_or_default();
+
+    let mut decoded = Vec::with_capacity(bundle.transactions().len());
+    for (i, tx) in bundle.transactions().iter().enumerate() {
+        let raw = match tx {
+            BundleTransaction::Signed(inner) => inner.rlp(),
+            BundleTransaction::Raw(inner) => inner.clone(),
+        };
+        decoded
+            .push(decode_raw_transaction(&raw).ok_or(LocalSimulationError::InvalidTransaction(i))?);
+    }
+
+    let traces: Vec<Vec<CallTrace>> = provider
+        .request(
+            "debug_traceCallMany",
+            (
+                [TraceBundle {
+                    transactions: decoded
+                        .iter()
+                        .map(|tx| CallRequest {
+                            from: tx.from,
+                            to: tx.to,
+                            gas: tx.gas,
+                            value: tx.value,
+                            data: tx.input.clone(),
+                        })
+                        .collect(),
+                    block_override: BlockOverride {
+                        number: bundle.block(),
+                        time: bundle.simulation_timestamp().map(U64::from),
+                    },
+                }],
+                SimulationContext {
+                    block_number: simulation_block,
+                    transaction_index: -1,
+                },
+                TraceConfig {
+                    tracer: "callTracer",
+                },
+            ),
+        )
+        .await?;
+    let traces = traces.into_iter().next().unwrap_or_default();
+    if traces.len() != decoded.len() {
+        return Err(LocalSimulationError::MissingTraces(traces.len()));
+    }
+
+    let transactions = decoded
+        .iter()
+        .zip(traces)
+        .map(|(tx, trace)| simulated_transaction(tx, trace, base_fee))
+        .collect();
+    Ok(simulated_bundle(transactions, simulation_block))
+}
+
+/// Whether the node rejected a request because it does not support the
+/// method, rather than because the request failed.
+fn is_unsupported(error: &ProviderError) -> bool {
+    let error = error.to_string().to_lowercase();
+    [
+        "-32601",
+        "method not found",
+        "does not exist",
+        "not available",
+        "not supported",
+        "unsupported",
+    ]
+    .iter()
+    .any(|message| error.contains(message))
+}
+
+fn simulated_transaction(
+    tx: &DecodedTransaction,
+    trace: CallTrace,
+    base_fee: U256,
+) -> SimulatedTransaction {
+    let priority_fee = tx.priority_fee(base_fee);
+    let gas_fees = trace.gas_used * priority_fee;
+
+    SimulatedTransaction {
+        hash: tx.hash,
+        coinbase_diff: gas_fees,
+        coinbase_tip: U256::zero(),
+        gas_price: base_fee + priority_fee,
+        gas_used: trace.gas_used,
+        gas_fees,
+        from: tx.from,
+        to: tx.to,
+        value: tx.value,
+        error: trace.error.map(|error| match trace.revert_reason {
+            Some(reason) => format!("{}: {}", error, reason),
+            None => error,
+        }),
+    }
+}
+
+fn simulated_bundle(
+    transactions: Vec<SimulatedTransaction>,
+    simulation_block: U64,
+) -> SimulatedBundle {
+    // Same as the relays, the bundle hash is the hash of the concatenated
+    // transaction hashes.
+    let hashes = transactions
+        .iter()
+        .flat_map(|tx| tx.hash.0)
+        .collect::<Vec<u8>>();
+    let coinbase_diff = transactions
+        .iter()
+        .fold(U256::zero(), |sum, tx| sum + tx.coinbase_diff);
+    let gas_used = transactions
+        .iter()
+        .fold(U256::zero(), |sum, tx| sum + tx.gas_used);
+
+    SimulatedBundle {
+        hash: keccak256(hashes).into(),
+        coinbase_diff,
+        coinbase_tip: U256::zero(),
+        gas_price: if gas_used.is_zero() {
+            U256::zero()
+        } else {
+            coinbase_diff / gas_used
+        },
+        gas_used,
+        gas_fees: coinbase_diff,
+        simulation_block,
+        transactions,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers_core::types::H256;
+
+    fn decoded_transaction(hash: u64, max_priority_fee_per_gas: Option<u64>) -> DecodedTransaction {
+        DecodedTransaction {
+            hash: H256::from_low_u64_be(hash),
+            from: Address::zero(),
+            to: Some(Address::zero()),
+            nonce: U256::zero(),
+            gas: 100_000.into(),
+            value: U256::zero(),
+            input: Bytes::default(),
+            max_fee_per_gas: 120.into(),
+            max_priority_fee_per_gas: max_priority_fee_per_gas.map(U256::from),
+        }
+    }
+
+    #[test]
+    fn local_simulation_results() {
+        let base_fee = U256::from(100);
+        let transactions = vec![
+            simulated_transaction(
+                &decoded_transaction(1, Some(5)),
+                CallTrace {
+                    gas_used: 21000.into(),
+                    error: None,
+                    revert_reason: None,
+                },
+                base_fee,
+            ),
+            // legacy transactions pay everything above the base fee
+            simulated_transaction(
+                &decoded_transaction(2, None),
+                CallTrace {
+                    gas_used: 50000.into(),
+                    error: Some("execution reverted".into()),
+                    revert_reason: Some("sold out".into()),
+                },
+                base_fee,
+            ),
+        ];
+
+        assert_eq!(transactions[0].gas_price, U256::from(105));
+        assert_eq!(transactions[0].coinbase_diff, U256::from(21000 * 5));
+        assert_eq!(transactions[1].coinbase_diff, U256::from(50000 * 20));
+        assert_eq!(
+            transactions[1].error.as_deref(),
+            Some("execution reverted: sold out")
+        );
+
+        let bundle = simulated_bundle(transactions, 1.into());
+        assert_eq!(bundle.gas_used, U256::from(71000));
+        assert_eq!(bundle.coinbase_diff, U256::from(21000 * 5 + 50000 * 20));
+        assert_eq!(bundle.gas_price, bundle.coinbase_diff / bundle.gas_used);
+        assert_eq!(bundle.simulation_block, U64::from(1));
+    }
+
+    #[test]
+    fn local_simulation_fallback() {
+        let error = |message: &str| ProviderError::CustomError(message.into());
+        assert!(is_unsupported(&error(
+            "(code: -32601, message: the method eth_callBundle does not exist/is not available, data: None)"
+        )));
+        assert!(!is_unsupported(&error(
+            "(code: -32000, message: nonce too low, data: None)"
+        )));
+    }
+}
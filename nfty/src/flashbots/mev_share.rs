@@ -0,0 +1,304 @@
+use crate::flashbots::bundle::BundleTransaction;
+use ethers_core::{
+    types::{Address, Bytes, TxHash, U256, U64},
+    utils::keccak256,
+};
+use serde::{Deserialize, Serialize};
+
+/// The version of the `mev_sendBundle` protocol the bundles are sent with.
+const MEV_SHARE_VERSION: &str = "v0.1";
+
+/// A hint about a transaction that is shared with searchers on MEV-Share.
+///
+/// See the [MEV-Share documentation][mev_share_hints] for what each hint reveals.
+///
+/// [mev_share_hints]: https://docs.flashbots.net/flashbots-protect/mev-share#hints
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PrivacyHint {
+    Calldata,
+    ContractAddress,
+    Logs,
+    FunctionSelector,
+    Hash,
+    TxHash,
+}
+
+/// An item in the body of a MEV-Share bundle.
+#[derive(Debug, Clone, Serialize)]
+#[serde(untagged)]
+pub enum MevShareBundleItem {
+    /// A transaction shared on MEV-Share, referenced by its hash.
+    Hash { hash: TxHash },
+    /// An RLP encoded signed transaction.
+    #[serde(rename_all = "camelCase")]
+    Tx { tx: Bytes, can_revert: bool },
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Inclusion {
+    block: U64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_block: Option<U64>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Validity {
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    refund: Vec<Refund>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    refund_config: Vec<RefundConfig>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Refund {
+    body_idx: usize,
+    percent: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct RefundConfig {
+    address: Address,
+    percent: u64,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+struct Privacy {
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    hints: Vec<PrivacyHint>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    builders: Vec<String>,
+}
+
+impl Validity {
+    fn is_empty(&self) -> bool {
+        self.refund.is_empty() && self.refund_config.is_empty()
+    }
+}
+
+impl Privacy {
+    fn is_empty(&self) -> bool {
+        self.hints.is_empty() && self.builders.is_empty()
+    }
+}
+
+/// A bundle that can be submitted to a relay using the MEV-Share
+/// [`mev_sendBundle`][mev_send_bundle] protocol.
+///
+/// Unlike a [`BundleRequest`](crate::flashbots::BundleRequest), a MEV-Share
+/// bundle targets a range of blocks on its own, can reference transactions
+/// shared on MEV-Share by their hash, and can ask for a share of the MEV it
+/// creates to be refunded.
+///
+/// Please note that some parameters are required, and submitting a bundle
+/// without them will get it rejected pre-flight. The required parameters
+/// include:
+///
+/// - At least one transaction ([`MevShareBundle::push_transaction`])
+/// - A target block ([`MevShareBundle::set_block`])
+///
+/// [mev_send_bundle]: https://docs.flashbots.net/flashbots-auction/advanced/rpc-endpoint#mev_sendbundle
+#[derive(Clone, Debug, Serialize)]
+pub struct MevShareBundle {
+    version: &'static str,
+    inclusion: Option<Inclusion>,
+    body: Vec<MevShareBundleItem>,
+    #[serde(skip_serializing_if = "Validity::is_empty")]
+    validity: Validity,
+    #[serde(skip_serializing_if = "Privacy::is_empty")]
+    privacy: Privacy,
+}
+
+impl Default for MevShareBundle {
+    fn default() -> Self {
+        Self {
+            version: MEV_SHARE_VERSION,
+            inclusion: None,
+            body: Vec::new(),
+            validity: Validity::default(),
+            privacy: Privacy::default(),
+        }
+    }
+}
+
+impl MevShareBundle {
+    /// Creates an empty MEV-Share bundle.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Adds a transaction to the bundle.
+    pub fn push_transaction<T: Into<BundleTransaction>>(&mut self, tx: T) -> &mut Self {
+        self.push_tx(tx.into(), false)
+    }
+
+    /// Adds a revertible transaction to the bundle.
+    ///
+    /// The bundle is still considered valid if the transaction reverts.
+    pub fn push_revertible_transaction<T: Into<BundleTransaction>>(&mut self, tx: T) -> &mut Self {
+        self.push_tx(tx.into(), true)
+    }
+
+    fn push_tx(&mut self, tx: BundleTransaction, can_revert: bool) -> &mut Self {
+        let tx = match tx {
+            BundleTransaction::Signed(inner) => inner.rlp(),
+            BundleTransaction::Raw(inner) => inner,
+        };
+        self.body.push(MevShareBundleItem::Tx { tx, can_revert });
+        self
+    }
+
+    /// Adds a transaction shared on MEV-Share to the bundle by its hash,
+    /// e.g. to backrun it.
+    pub fn push_transaction_hash(&mut self, hash: TxHash) -> &mut Self {
+        self.body.push(MevShareBundleItem::Hash { hash });
+        self
+    }
+
+    /// Get a reference to the body of the bundle.
+    pub fn body(&self) -> &Vec<MevShareBundleItem> {
+        &self.body
+    }
+
+    /// Get a list of transaction hashes in the bundle.
+    pub fn transaction_hashes(&self) -> Vec<TxHash> {
+        self.body
+            .iter()
+            .map(|item| match item {
+                MevShareBundleItem::Hash { hash } => *hash,
+                MevShareBundleItem::Tx { tx, .. } => keccak256(tx).into(),
+            })
+            .collect()
+    }
+
+    /// Get the target block (if any).
+    pub fn block(&self) -> Option<U64> {
+        self.inclusion.as_ref().map(|i| i.block)
+    }
+
+    /// Set the target block of the bundle.
+    pub fn set_block(&mut self, block: U64) -> &mut Self {
+        let max_block = self.max_block();
+        self.inclusion = Some(Inclusion { block, max_block });
+        self
+    }
+
+    /// Get the last block the bundle is valid for (if any).
+    pub fn max_block(&self) -> Option<U64> {
+        self.inclusion.as_ref().and_then(|i| i.max_block)
+    }
+
+    /// Set the last block the bundle is valid for.
+    ///
+    /// The target block must be set first, see [`MevShareBundle::set_block`].
+    pub fn set_max_block(&mut self, block: U64) -> &mut Self {
+        if let Some(inclusion) = self.inclusion.as_mut() {
+            inclusion.max_block = Some(block);
+        }
+        self
+    }
+
+    /// Ask for `percent` of the MEV created by the bundle to be refunded to
+    /// the signer of the transaction at `body_idx`.
+    pub fn push_refund(&mut self, body_idx: usize, percent: u64) -> &mut Self {
+        self.validity.refund.push(Refund { body_idx, percent });
+        self
+    }
+
+    /// Send `percent` of the refund to `address` instead of the signer.
+    ///
+    /// The percentages of all refund addresses should add up to 100.
+    pub fn push_refund_address(&mut self, address: Address, percent: u64) -> &mut Self {
+        self.validity
+            .refund_config
+            .push(RefundConfig { address, percent });
+        self
+    }
+
+    /// Share a hint about the bundle with searchers.
+    pub fn push_hint(&mut self, hint: PrivacyHint) -> &mut Self {
+        if !self.privacy.hints.contains(&hint) {
+            self.privacy.hints.push(hint);
+        }
+        self
+    }
+
+    /// Set the builders the bundle may be sent to.
+    pub fn set_builders(&mut self, builders: Vec<String>) -> &mut Self {
+        self.privacy.builders = builders;
+        self
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct SimMevBundleParams {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) parent_block: Option<U64>,
+}
+
+/// Details of a MEV-Share bundle simulated with `mev_simBundle`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SimulatedMevBundle {
+    /// Whether every non-revertible transaction in the bundle succeeded.
+    pub success: bool,
+    /// The reason the simulation failed (if it did).
+    pub error: Option<String>,
+    /// The block at which this bundle was simulated.
+    pub state_block: U64,
+    /// The gas price of the bundle, including refunds.
+    pub mev_gas_price: U256,
+    /// The profit of the bundle for the builder.
+    pub profit: U256,
+    /// The value that can be refunded to the bundle.
+    pub refundable_value: U256,
+    /// The total amount of gas used in this bundle.
+    pub gas_used: U64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mev_share_bundle_serialize() {
+        let mut bundle = MevShareBundle::new();
+        bundle
+            .push_transaction_hash(
+                "0x669b4704a7d993a946cdd6e2f95233f308ce0c4649d2e04944e8299efcaa098a"
+                    .parse()
+                    .unwrap(),
+            )
+            .push_transaction(Bytes::from(vec![0x1]))
+            .set_block(2.into())
+            .set_max_block(4.into())
+            .push_refund(1, 90)
+            .push_refund_address(Address::zero(), 100)
+            .push_hint(PrivacyHint::Calldata)
+            .push_hint(PrivacyHint::Calldata)
+            .push_hint(PrivacyHint::TxHash)
+            .set_builders(vec!["flashbots".into()]);
+
+        assert_eq!(
+            &serde_json::to_string(&bundle).unwrap(),
+            r#"{"version":"v0.1","inclusion":{"block":"0x2","maxBlock":"0x4"},"body":[{"hash":"0x669b4704a7d993a946cdd6e2f95233f308ce0c4649d2e04944e8299efcaa098a"},{"tx":"0x01","canRevert":false}],"validity":{"refund":[{"bodyIdx":1,"percent":90}],"refundConfig":[{"address":"0x0000000000000000000000000000000000000000","percent":100}]},"privacy":{"hints":["calldata","tx_hash"],"builders":["flashbots"]}}"#
+        );
+    }
+
+    #[test]
+    fn mev_share_bundle_serialize_minimal() {
+        let mut bundle = MevShareBundle::new();
+        bundle
+            .push_revertible_transaction(Bytes::from(vec![0x1]))
+            .set_block(2.into());
+
+        assert_eq!(
+            &serde_json::to_string(&bundle).unwrap(),
+            r#"{"version":"v0.1","inclusion":{"block":"0x2"},"body":[{"tx":"0x01","canRevert":true}]}"#
+        );
+    }
+}
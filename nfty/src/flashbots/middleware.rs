@@ -1,18 +1,40 @@
 use crate::flashbots::{
-    bundle::{BundleHash, BundleRequest, BundleStats, SimulatedBundle},
+    block_feed::BlockFeed,
+    bundle::{
+        BundleHash, BundleRequest, BundleStats, BundleStatsV2, BundleTransaction, SimulatedBundle,
+    },
+    decode_raw_transaction,
+    health::RelayHealth,
+    local_simulation::{self, LocalSimulationError},
+    mev_share::{MevShareBundle, SimMevBundleParams, SimulatedMevBundle},
     pending_bundle::PendingBundle,
-    relay::{GetBundleStatsParams, GetUserStatsParams, Relay, RelayError, SendBundleResponse},
+    postmortem::{BlockFees, MissReason},
+    private_transaction::PrivateTransaction,
+    relay::{
+        CancelBundleParams, CancelPrivateTransactionParams, GetBundleStatsParams,
+        GetUserStatsParams, Relay, RelayError, RelaySubmission, SendBundleResponse,
+        SendPrivateTransactionParams,
+    },
+    simulation::{RelaySimulation, SimulationComparison},
     UserStats,
 };
 use async_trait::async_trait;
 use ethers_core::{
-    types::{BlockNumber, Bytes, U64},
+    types::{BlockNumber, Bytes, TxHash, H256, U64},
     utils::keccak256,
 };
-use ethers_providers::{FromErr, Middleware, PendingTransaction};
+use ethers_providers::{FromErr, Http, Middleware, PendingTransaction, Provider, ProviderError};
 use ethers_signers::Signer;
+use futures_util::future::join_all;
+use serde::{de::DeserializeOwned, Serialize};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Mutex,
+    time::{Duration, Instant},
+};
 use thiserror::Error;
 use url::Url;
+use uuid::Uuid;
 
 /// Errors for the Flashbots middleware.
 #[derive(Error, Debug)]
@@ -37,6 +59,16 @@ pub enum FlashbotsMiddlewareError<M: Middleware, S: Signer> {
     /// An error occured in one of the middlewares.
     #[error("{0}")]
     MiddlewareError(M::Error),
+    /// An error occured while interacting with the RPC endpoint.
+    #[error(transparent)]
+    ProviderError(#[from] ProviderError),
+    /// The bundle could not be simulated locally.
+    #[error(transparent)]
+    LocalSimulationError(#[from] LocalSimulationError),
+    /// No relay responded to the method, e.g. because none of them
+    /// supports it.
+    #[error("no relay accepted {0}")]
+    NoRelayResponse(String),
 }
 
 impl<M: Middleware, S: Signer> FromErr<M::Error> for FlashbotsMiddlewareError<M, S> {
@@ -104,6 +136,12 @@ impl<M: Middleware, S: Signer> FromErr<M::Error> for FlashbotsMiddlewareError<M,
 pub struct FlashbotsMiddleware<M, S> {
     inner: M,
     relays: Vec<Relay<S>>,
+    simulation_relay: Option<Relay<S>>,
+    simulation_provider: Option<Provider<Http>>,
+    block_feed: Option<BlockFeed>,
+    /// The replacement UUIDs every bundle of a range was sent with, by the
+    /// replacement UUID of the bundle the range was made from.
+    replacement_uuids: Mutex<HashMap<Uuid, HashSet<Uuid>>>,
 }
 
 impl<M: Middleware, S: Signer + Clone> FlashbotsMiddleware<M, S> {
@@ -121,6 +159,27 @@ impl<M: Middleware, S: Signer + Clone> FlashbotsMiddleware<M, S> {
                 .into_iter()
                 .map(|u| Relay::new(u, relay_signer.clone()))
                 .collect(),
+            simulation_relay: None,
+            simulation_provider: None,
+            block_feed: None,
+            replacement_uuids: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Initialize a new Flashbots middleware with relays that have been
+    /// configured individually, e.g. with their own authentication.
+    pub fn with_relays(inner: M, relays: Vec<Relay<S>>) -> Self {
+        if relays.is_empty() {
+            panic!("need at least 1 relay");
+        }
+
+        Self {
+            inner,
+            relays,
+            simulation_relay: None,
+            simulation_provider: None,
+            block_feed: None,
+            replacement_uuids: Mutex::new(HashMap::new()),
         }
     }
 
@@ -129,6 +188,43 @@ impl<M: Middleware, S: Signer + Clone> FlashbotsMiddleware<M, S> {
         &self.relays
     }
 
+    /// Set the relay used for bundle simulation and stats.
+    ///
+    /// The relay does not have to be one of the relays bundles are sent to.
+    /// By default, the first relay is used.
+    pub fn set_simulation_relay(&mut self, url: impl Into<Url>) -> &mut Self {
+        let url = url.into();
+        self.simulation_relay = Some(
+            self.relays
+                .iter()
+                .find(|r| *r.url() == url)
+                .cloned()
+                .unwrap_or_else(|| Relay::new(url, self.relays[0].signer().clone())),
+        );
+        self
+    }
+
+    /// Set the node used for local bundle simulation.
+    ///
+    /// By default, bundles are simulated locally against the inner provider.
+    pub fn set_simulation_provider(&mut self, provider: Provider<Http>) -> &mut Self {
+        self.simulation_provider = Some(provider);
+        self
+    }
+
+    /// Set the block feed that pending bundles are driven by.
+    ///
+    /// Without a block feed, pending bundles poll for their target blocks.
+    pub fn set_block_feed(&mut self, feed: BlockFeed) -> &mut Self {
+        self.block_feed = Some(feed);
+        self
+    }
+
+    /// Get the relay used for bundle simulation and stats.
+    pub fn simulation_relay(&self) -> &Relay<S> {
+        self.simulation_relay.as_ref().unwrap_or(&self.relays[0])
+    }
+
     /// Simulate a bundle.
     ///
     /// See [`eth_callBundle`][fb_callBundle] for more information.
@@ -144,12 +240,71 @@ impl<M: Middleware, S: Signer + Clone> FlashbotsMiddleware<M, S> {
             .and(bundle.simulation_timestamp())
             .ok_or(FlashbotsMiddlewareError::MissingParameters)?;
 
-        self.relays[0]
+        self.simulation_relay()
             .request("eth_callBundle", [bundle])
             .await
             .map_err(FlashbotsMiddlewareError::RelayError)
     }
 
+    /// Simulate a bundle against a node instead of a relay.
+    ///
+    /// The node is either the one set with
+    /// [`FlashbotsMiddleware::set_simulation_provider`], or the inner provider.
+    /// It is asked to simulate the bundle with `eth_callBundle`, and if it
+    /// does not support it, the transactions are traced in order with
+    /// `debug_traceCallMany`.
+    pub async fn simulate_bundle_locally(
+        &self,
+        bundle: &BundleRequest,
+    ) -> Result<SimulatedBundle, FlashbotsMiddlewareError<M, S>> {
+        bundle
+            .block()
+            .and(bundle.simulation_block())
+            .and(bundle.simulation_timestamp())
+            .ok_or(FlashbotsMiddlewareError::MissingParameters)?;
+
+        Ok(match self.simulation_provider.as_ref() {
+            Some(provider) => local_simulation::simulate_bundle(provider, bundle).await?,
+            None => local_simulation::simulate_bundle(self.provider(), bundle).await?,
+        })
+    }
+
+    /// Simulate a bundle on every relay and compare the results.
+    ///
+    /// The simulation relay is always simulated on first, so that its
+    /// result is used as the baseline when it succeeds. Relays that do not
+    /// support `eth_callBundle` are reported as failed simulations.
+    pub async fn simulate_bundle_on_all(
+        &self,
+        bundle: &BundleRequest,
+    ) -> Result<SimulationComparison, FlashbotsMiddlewareError<M, S>> {
+        bundle
+            .block()
+            .and(bundle.simulation_block())
+            .and(bundle.simulation_timestamp())
+            .ok_or(FlashbotsMiddlewareError::MissingParameters)?;
+
+        let simulation_relay = self.simulation_relay();
+        let relays = std::iter::once(simulation_relay).chain(
+            self.relays
+                .iter()
+                .filter(|r| r.url() != simulation_relay.url() && r.supports("eth_callBundle")),
+        );
+
+        let simulations = self
+            .fan_out_to::<_, SimulatedBundle>(relays, "eth_callBundle", [bundle])
+            .await
+            .into_iter()
+            .map(|(relay, latency, result)| RelaySimulation {
+                relay: relay.url().clone(),
+                latency,
+                result: result.map_err(|e| e.to_string()),
+            })
+            .collect();
+
+        Ok(SimulationComparison { simulations })
+    }
+
     /// Send a bundle to the relayer.
     ///
     /// See [`eth_sendBundle`][fb_sendBundle] for more information.
@@ -159,9 +314,26 @@ impl<M: Middleware, S: Signer + Clone> FlashbotsMiddleware<M, S> {
         &self,
         bundle: &BundleRequest,
     ) -> Result<PendingBundle<'_, <Self as Middleware>::Provider>, FlashbotsMiddlewareError<M, S>>
+    {
+        self.send_bundle_range(bundle, 1).await
+    }
+
+    /// Send a bundle to the relayer for `blocks` consecutive blocks,
+    /// starting at the target block of the bundle.
+    ///
+    /// The same signed bundle is submitted once per block, and the returned
+    /// [`PendingBundle`] resolves as soon as the bundle lands in any of them.
+    /// Every block gets its own replacement UUID (see
+    /// [`BundleRequest::for_block`]), which are all cancelled along with the
+    /// replacement UUID of the bundle.
+    pub async fn send_bundle_range(
+        &self,
+        bundle: &BundleRequest,
+        blocks: u64,
+    ) -> Result<PendingBundle<'_, <Self as Middleware>::Provider>, FlashbotsMiddlewareError<M, S>>
     {
         // The target block must be set
-        bundle
+        let first_block = bundle
             .block()
             .ok_or(FlashbotsMiddlewareError::MissingParameters)?;
 
@@ -170,57 +342,319 @@ impl<M: Middleware, S: Signer + Clone> FlashbotsMiddleware<M, S> {
             return Err(FlashbotsMiddlewareError::MissingParameters);
         }
 
-        let mut resp: Result<SendBundleResponse, FlashbotsMiddlewareError<M, S>> =
-            Err(FlashbotsMiddlewareError::MissingParameters);
-        for r in &self.relays {
-            let response: Result<SendBundleResponse, FlashbotsMiddlewareError<M, S>> = r
-                .request("eth_sendBundle", [bundle])
-                .await
-                .map_err(FlashbotsMiddlewareError::RelayError);
+        let bundles = (0..blocks.max(1))
+            .map(|i| bundle.for_block(first_block + i))
+            .collect::<Vec<_>>();
+        if let Some(replacement_uuid) = bundle.replacement_uuid() {
+            self.replacement_uuids
+                .lock()
+                .unwrap()
+                .entry(replacement_uuid)
+                .or_default()
+                .extend(bundles.iter().filter_map(|b| b.replacement_uuid()));
+        }
+        let responses = join_all(
+            bundles
+                .iter()
+                .map(|bundle| self.fan_out::<_, SendBundleResponse>("eth_sendBundle", [bundle])),
+        )
+        .await;
+
+        let submissions =
+            Self::collect_submissions("eth_sendBundle", responses.into_iter().flatten(), |resp| {
+                Some(resp.bundle_hash)
+            })?;
+        // The hash of the most reliable relay that accepted the bundle.
+        let bundle_hash = submissions
+            .iter()
+            .find_map(|s| s.hash)
+            .ok_or_else(|| FlashbotsMiddlewareError::NoRelayResponse("eth_sendBundle".into()))?;
+
+        let pending_bundle = PendingBundle::new(
+            bundle_hash,
+            first_block,
+            bundles.last().and_then(|b| b.block()).unwrap(),
+            bundle.transaction_hashes(),
+            submissions,
+            self.provider(),
+        );
+
+        Ok(match self.block_feed.as_ref() {
+            Some(feed) => pending_bundle.block_feed(feed.clone()),
+            None => pending_bundle,
+        })
+    }
+
+    /// Simulate a MEV-Share bundle on top of `parent_block`, or on top of
+    /// the latest block if it is not set.
+    ///
+    /// See [`mev_simBundle`][fb_simBundle] for more information.
+    ///
+    /// [fb_simBundle]: https://docs.flashbots.net/flashbots-auction/advanced/rpc-endpoint#mev_simbundle
+    pub async fn simulate_mev_bundle(
+        &self,
+        bundle: &MevShareBundle,
+        parent_block: Option<U64>,
+    ) -> Result<SimulatedMevBundle, FlashbotsMiddlewareError<M, S>> {
+        bundle
+            .block()
+            .ok_or(FlashbotsMiddlewareError::MissingParameters)?;
+
+        self.simulation_relay()
+            .request(
+                "mev_simBundle",
+                (bundle, SimMevBundleParams { parent_block }),
+            )
+            .await
+            .map_err(FlashbotsMiddlewareError::RelayError)
+    }
+
+    /// Send a MEV-Share bundle to the relayer.
+    ///
+    /// The returned [`PendingBundle`] resolves as soon as the bundle lands in
+    /// any block between its target block and its max block.
+    ///
+    /// See [`mev_sendBundle`][fb_sendMevBundle] for more information.
+    ///
+    /// [fb_sendMevBundle]: https://docs.flashbots.net/flashbots-auction/advanced/rpc-endpoint#mev_sendbundle
+    pub async fn send_mev_bundle(
+        &self,
+        bundle: &MevShareBundle,
+    ) -> Result<PendingBundle<'_, <Self as Middleware>::Provider>, FlashbotsMiddlewareError<M, S>>
+    {
+        // The target block must be set
+        let block = bundle
+            .block()
+            .ok_or(FlashbotsMiddlewareError::MissingParameters)?;
+        if bundle.body().is_empty() {
+            return Err(FlashbotsMiddlewareError::MissingParameters);
+        }
 
-            if let Err(e) = response {
-                if resp.is_ok() {
-                    continue;
+        let responses = self
+            .fan_out::<_, SendBundleResponse>("mev_sendBundle", [bundle])
+            .await;
+        let submissions =
+            Self::collect_submissions("mev_sendBundle", responses, |resp| Some(resp.bundle_hash))?;
+        // The hash of the most reliable relay that accepted the bundle.
+        let bundle_hash = submissions
+            .iter()
+            .find_map(|s| s.hash)
+            .ok_or_else(|| FlashbotsMiddlewareError::NoRelayResponse("mev_sendBundle".into()))?;
+
+        let pending_bundle = PendingBundle::new(
+            bundle_hash,
+            block,
+            bundle.max_block().unwrap_or(block),
+            bundle.transaction_hashes(),
+            submissions,
+            self.provider(),
+        );
+
+        Ok(match self.block_feed.as_ref() {
+            Some(feed) => pending_bundle.block_feed(feed.clone()),
+            None => pending_bundle,
+        })
+    }
+
+    /// Cancel a bundle on every relay.
+    ///
+    /// Only bundles that were sent with a replacement UUID (see
+    /// [`BundleRequest::set_replacement_uuid`]) can be cancelled. The bundles
+    /// sent for every block of a range with that UUID are all cancelled.
+    pub async fn cancel_bundle(
+        &self,
+        replacement_uuid: Uuid,
+    ) -> Result<Vec<RelaySubmission>, FlashbotsMiddlewareError<M, S>> {
+        let replacement_uuids = self
+            .replacement_uuids
+            .lock()
+            .unwrap()
+            .remove(&replacement_uuid)
+            .unwrap_or_else(|| [replacement_uuid].into_iter().collect());
+        let responses = join_all(replacement_uuids.into_iter().map(|replacement_uuid| {
+            self.fan_out::<_, serde_json::Value>(
+                "eth_cancelBundle",
+                [CancelBundleParams { replacement_uuid }],
+            )
+        }))
+        .await;
+
+        Self::collect_submissions("eth_cancelBundle", responses.into_iter().flatten(), |_| {
+            None
+        })
+    }
+
+    /// Send a private transaction to every relay.
+    ///
+    /// The relays forward the transaction to builders until it is included,
+    /// or until `max_block_number` is reached.
+    ///
+    /// See [`eth_sendPrivateTransaction`][fb_sendPrivateTransaction] for more information.
+    ///
+    /// [fb_sendPrivateTransaction]: https://docs.flashbots.net/flashbots-protect/rpc/private-transactions#eth_sendprivatetransaction
+    pub async fn send_private_transaction(
+        &self,
+        tx: Bytes,
+        max_block_number: Option<U64>,
+    ) -> Result<PrivateTransaction, FlashbotsMiddlewareError<M, S>> {
+        let hash = TxHash::from(keccak256(&tx));
+        let responses = self
+            .fan_out::<_, TxHash>(
+                "eth_sendPrivateTransaction",
+                [SendPrivateTransactionParams {
+                    tx,
+                    max_block_number,
+                }],
+            )
+            .await;
+
+        Ok(PrivateTransaction {
+            hash,
+            max_block_number,
+            submissions: Self::collect_submissions(
+                "eth_sendPrivateTransaction",
+                responses,
+                |hash| Some(*hash),
+            )?,
+        })
+    }
+
+    /// Cancel a private transaction on every relay.
+    ///
+    /// See [`eth_cancelPrivateTransaction`][fb_cancelPrivateTransaction] for more information.
+    ///
+    /// [fb_cancelPrivateTransaction]: https://docs.flashbots.net/flashbots-protect/rpc/private-transactions#eth_cancelprivatetransaction
+    pub async fn cancel_private_transaction(
+        &self,
+        tx_hash: TxHash,
+    ) -> Result<Vec<RelaySubmission>, FlashbotsMiddlewareError<M, S>> {
+        let responses = self
+            .fan_out::<_, bool>(
+                "eth_cancelPrivateTransaction",
+                [CancelPrivateTransactionParams { tx_hash }],
+            )
+            .await;
+
+        Self::collect_submissions("eth_cancelPrivateTransaction", responses, |_| None)
+    }
+
+    /// Records the outcome of a request for every relay.
+    ///
+    /// The submissions keep the order of the responses, so with `fan_out`
+    /// the most reliable relay that responded comes first, and its error is
+    /// the one returned if every relay failed.
+    ///
+    /// This only fails if every relay failed or no relay was sent `method`,
+    /// the caller can inspect the individual submissions for partial
+    /// failures.
+    fn collect_submissions<'r, R>(
+        method: &str,
+        responses: impl IntoIterator<Item = (&'r Relay<S>, Duration, Result<R, RelayError<S>>)>,
+        hash: impl Fn(&R) -> Option<H256>,
+    ) -> Result<Vec<RelaySubmission>, FlashbotsMiddlewareError<M, S>>
+    where
+        S: 'r,
+    {
+        let mut first_error = None;
+        let mut submissions = Vec::new();
+        for (relay, latency, response) in responses {
+            match response {
+                Ok(resp) => submissions.push(RelaySubmission {
+                    relay: relay.url().clone(),
+                    hash: hash(&resp),
+                    latency,
+                    error: None,
+                }),
+                Err(e) => {
+                    submissions.push(RelaySubmission {
+                        relay: relay.url().clone(),
+                        hash: None,
+                        latency,
+                        error: Some(e.to_string()),
+                    });
+                    first_error.get_or_insert(e);
                 }
+            }
+        }
+
+        match first_error {
+            Some(e) if !submissions.iter().any(|s| s.is_ok()) => {
+                Err(FlashbotsMiddlewareError::RelayError(e))
+            }
+            _ if submissions.is_empty() => {
+                Err(FlashbotsMiddlewareError::NoRelayResponse(method.into()))
+            }
+            _ => Ok(submissions),
+        }
+    }
+
+    /// Get the health of every relay.
+    pub fn relay_health(&self) -> Vec<(Url, RelayHealth)> {
+        self.relays
+            .iter()
+            .map(|relay| (relay.url().clone(), relay.health()))
+            .collect()
+    }
 
-                resp = Err(e);
-            } else {
-                resp = response;
+    /// Record that a bundle was included, for every relay that accepted it.
+    pub fn record_inclusion(&self, submissions: &[RelaySubmission]) {
+        for relay in &self.relays {
+            if submissions
+                .iter()
+                .any(|s| s.is_ok() && s.relay == *relay.url())
+            {
+                relay.record_inclusion();
             }
         }
+    }
 
-        /*
-        let results = self
+    /// Get the relays to send `method` to, from the most to the least
+    /// reliable, skipping relays that do not support it or are in cool-down.
+    ///
+    /// If every relay is in cool-down, they are all used anyway.
+    fn available_relays(&self, method: &str) -> Vec<&Relay<S>> {
+        let mut relays = self
             .relays
             .iter()
-            .map(|r| {
-                r.request("eth_sendBundle", [bundle])
-                    .await
-                    .map_err(FlashbotsMiddlewareError::RelayError)
-            })
+            .filter(|relay| relay.supports(method))
+            .map(|relay| (relay, relay.health()))
             .collect::<Vec<_>>();
+        if relays.iter().any(|(_, health)| !health.is_cooling_down()) {
+            relays.retain(|(_, health)| !health.is_cooling_down());
+        }
 
-        let all_err = results.iter().all(|r| r.is_err());
+        relays.sort_by(|(_, a), (_, b)| a.compare(b));
+        relays.into_iter().map(|(relay, _)| relay).collect()
+    }
 
-        if all_err {
-            Err(results.first().unwrap().err().unwrap())
-        } else {
-            let resp: SendBundleResponse = results.iter().filter_map(|r| r.ok()).nth(0).unwrap();
-            Ok(PendingBundle::new(
-                resp.bundle_hash,
-                bundle.block().unwrap(),
-                bundle.transaction_hashes(),
-                self.provider(),
-            ))
-        }
-        */
+    /// Sends the same request to every available relay concurrently.
+    ///
+    /// The responses are returned from the most to the least reliable relay,
+    /// along with the time each relay took to respond, so callers that only
+    /// need one result take it from the most reliable relay.
+    async fn fan_out<T: Serialize + Send + Sync, R: DeserializeOwned>(
+        &self,
+        method: &str,
+        params: T,
+    ) -> Vec<(&Relay<S>, Duration, Result<R, RelayError<S>>)> {
+        self.fan_out_to(self.available_relays(method).into_iter(), method, params)
+            .await
+    }
 
-        Ok(PendingBundle::new(
-            resp?.bundle_hash,
-            bundle.block().unwrap(),
-            bundle.transaction_hashes(),
-            self.provider(),
-        ))
+    /// Sends the same request to the given relays concurrently.
+    async fn fan_out_to<'r, T: Serialize + Send + Sync, R: DeserializeOwned>(
+        &self,
+        relays: impl Iterator<Item = &'r Relay<S>>,
+        method: &str,
+        params: T,
+    ) -> Vec<(&'r Relay<S>, Duration, Result<R, RelayError<S>>)> {
+        let params = &params;
+        join_all(relays.map(|relay| async move {
+            let start = Instant::now();
+            let response = relay.request(method, params).await;
+            (relay, start.elapsed(), response)
+        }))
+        .await
     }
 
     /// Get stats for a particular bundle.
@@ -229,7 +663,7 @@ impl<M: Middleware, S: Signer + Clone> FlashbotsMiddleware<M, S> {
         bundle_hash: BundleHash,
         block_number: U64,
     ) -> Result<BundleStats, FlashbotsMiddlewareError<M, S>> {
-        self.relays[0]
+        self.simulation_relay()
             .request(
                 "flashbots_getBundleStats",
                 [GetBundleStatsParams {
@@ -241,6 +675,146 @@ impl<M: Middleware, S: Signer + Clone> FlashbotsMiddleware<M, S> {
             .map_err(FlashbotsMiddlewareError::RelayError)
     }
 
+    /// Get stats for a particular bundle, including which builders
+    /// considered it.
+    pub async fn get_bundle_stats_v2(
+        &self,
+        bundle_hash: BundleHash,
+        block_number: U64,
+    ) -> Result<BundleStatsV2, FlashbotsMiddlewareError<M, S>> {
+        self.simulation_relay()
+            .request(
+                "flashbots_getBundleStatsV2",
+                [GetBundleStatsParams {
+                    bundle_hash,
+                    block_number,
+                }],
+            )
+            .await
+            .map_err(FlashbotsMiddlewareError::RelayError)
+    }
+
+    /// Figure out why a bundle was not included in `block`.
+    ///
+    /// The checks run from the most to the least conclusive: whether the
+    /// nonce of a bundle transaction was used by another transaction,
+    /// whether the bundle still simulates on top of the parent of `block`,
+    /// whether it paid less per gas than the transactions in `block`, and
+    /// finally what the relay reports about the bundle.
+    pub async fn explain_miss(
+        &self,
+        bundle: &BundleRequest,
+        bundle_hash: BundleHash,
+        block: U64,
+    ) -> Result<MissReason, FlashbotsMiddlewareError<M, S>> {
+        for tx in bundle.transactions() {
+            let (transaction, from, nonce) = match tx {
+                BundleTransaction::Signed(inner) => (inner.hash, inner.from, inner.nonce),
+                BundleTransaction::Raw(inner) => match decode_raw_transaction(inner) {
+                    Some(decoded) => (decoded.hash, decoded.from, decoded.nonce),
+                    None => continue,
+                },
+            };
+
+            let next_nonce = self
+                .inner
+                .get_transaction_count(from, Some(BlockNumber::Number(block).into()))
+                .await
+                .map_err(FlashbotsMiddlewareError::MiddlewareError)?;
+            if next_nonce > nonce {
+                return Ok(MissReason::NonceConsumed { transaction, nonce });
+            }
+        }
+
+        let target_block = self
+            .inner
+            .get_block(block)
+            .await
+            .map_err(FlashbotsMiddlewareError::MiddlewareError)?;
+        let timestamp = match target_block {
+            Some(target_block) => target_block.timestamp.as_u64(),
+            None => return Ok(MissReason::Unknown),
+        };
+
+        let mut simulation = bundle.clone();
+        simulation
+            .set_block(block)
+            .set_simulation_block(block - 1)
+            .set_simulation_timestamp(timestamp);
+        let simulated_bundle = match self.simulate_bundle(&simulation).await {
+            Ok(simulated_bundle) => simulated_bundle,
+            Err(e) => return Ok(MissReason::SimulationFailed(e.to_string())),
+        };
+
+        let revertible = bundle.revertible_transaction_hashes();
+        if let Some(tx) = simulated_bundle
+            .transactions
+            .iter()
+            .find(|tx| tx.error.is_some() && !revertible.contains(&tx.hash))
+        {
+            return Ok(MissReason::Reverted {
+                transaction: tx.hash,
+                error: tx.error.clone().unwrap_or_default(),
+            });
+        }
+
+        let fees: BlockFees = self
+            .provider()
+            .request("eth_getBlockByNumber", (block, true))
+            .await?;
+        if let Some(block_minimum) = fees.minimum_priority_fee() {
+            let effective_gas_price = simulated_bundle.effective_gas_price();
+            if effective_gas_price < block_minimum {
+                return Ok(MissReason::Underpriced {
+                    effective_gas_price,
+                    block_minimum,
+                });
+            }
+        }
+
+        // Not every relay supports the second version of the stats yet, so
+        // both are asked for in one batch and the first one is preferred.
+        let params = serde_json::json!([GetBundleStatsParams {
+            bundle_hash,
+            block_number: block,
+        }]);
+        let mut stats = match self
+            .simulation_relay()
+            .batch_request(&[
+                ("flashbots_getBundleStatsV2", params.clone()),
+                ("flashbots_getBundleStats", params),
+            ])
+            .await
+        {
+            Ok(stats) => stats.into_iter(),
+            Err(_) => return Ok(MissReason::Unknown),
+        };
+        let stats_v2 = stats
+            .next()
+            .and_then(Result::ok)
+            .and_then(|stats| serde_json::from_value::<BundleStatsV2>(stats).ok());
+        let stats_v1 = stats
+            .next()
+            .and_then(Result::ok)
+            .and_then(|stats| serde_json::from_value::<BundleStats>(stats).ok());
+        let (is_simulated, seen_by_builder) = match (stats_v2, stats_v1) {
+            (Some(stats), _) => (
+                stats.is_simulated,
+                !stats.considered_by_builders_at.is_empty(),
+            ),
+            (None, Some(stats)) => (stats.is_simulated, stats.is_sent_to_miners),
+            (None, None) => return Ok(MissReason::Unknown),
+        };
+
+        Ok(if !is_simulated {
+            MissReason::NotSimulated
+        } else if !seen_by_builder {
+            MissReason::NeverSeenByBuilder
+        } else {
+            MissReason::Outbid
+        })
+    }
+
     /// Get stats for your searcher identity.
     ///
     /// Your searcher identity is determined by the signer you
@@ -252,7 +826,7 @@ impl<M: Middleware, S: Signer + Clone> FlashbotsMiddleware<M, S> {
             .await
             .map_err(FlashbotsMiddlewareError::MiddlewareError)?;
 
-        self.relays[0]
+        self.simulation_relay()
             .request(
                 "flashbots_getUserStats",
                 [GetUserStatsParams {
@@ -8,12 +8,30 @@
 #![allow(dead_code)]
 mod bundle;
 pub use bundle::{
-    BundleHash, BundleRequest, BundleStats, BundleTransaction, SimulatedBundle,
-    SimulatedTransaction,
+    BuilderTimestamp, BundleHash, BundleRequest, BundleStats, BundleStatsV2, BundleTransaction,
+    CoinbasePayment, SimulatedBundle, SimulatedTransaction, COINBASE_PAYMENT_GAS,
 };
 
+mod block_feed;
+pub use block_feed::{spawn_block_feed, BlockArrival, BlockFeed};
+
+mod health;
+pub use health::{RelayHealth, COOL_DOWN, FAILURE_THRESHOLD};
+
+mod local_simulation;
+pub use local_simulation::LocalSimulationError;
+
+mod mev_share;
+pub use mev_share::{MevShareBundle, MevShareBundleItem, PrivacyHint, SimulatedMevBundle};
+
 mod pending_bundle;
-pub use pending_bundle::{PendingBundle, PendingBundleError};
+pub use pending_bundle::{IncludedBundle, PendingBundle, PendingBundleError};
+
+mod postmortem;
+pub use postmortem::MissReason;
+
+mod private_transaction;
+pub use private_transaction::PrivateTransaction;
 
 mod user;
 pub use user::UserStats;
@@ -23,6 +41,12 @@ pub use middleware::{FlashbotsMiddleware, FlashbotsMiddlewareError};
 
 mod jsonrpc;
 mod relay;
-pub use relay::{Relay, RelayError};
+pub use relay::{Relay, RelayAuth, RelayError, RelaySubmission, SignaturePayload};
+
+mod simulation;
+pub use simulation::{RelaySimulation, SimulationComparison, SimulationDifference};
+
+mod transaction;
+pub(crate) use transaction::decode_raw_transaction;
 
 mod utils;
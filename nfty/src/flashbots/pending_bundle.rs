@@ -1,4 +1,4 @@
-use crate::flashbots::bundle::BundleHash;
+use crate::flashbots::{block_feed::BlockFeed, bundle::BundleHash, relay::RelaySubmission};
 use ethers_core::types::{Block, TxHash, U64};
 use ethers_providers::{
     interval, JsonRpcClient, Middleware, Provider, ProviderError, DEFAULT_POLL_INTERVAL,
@@ -10,17 +10,26 @@ use std::{
     future::Future,
     pin::Pin,
     task::{Context, Poll},
+    time::{Duration, Instant},
 };
 use thiserror::Error;
 
 /// A pending bundle is one that has been submitted to a relay,
 /// but not yet included.
 ///
-/// You can `await` the pending bundle. When the target block of the
-/// bundle has been included in the chain the future will resolve,
-/// either with the bundle hash indicating that the bundle was
-/// included in the target block, or with an error indicating
-/// that the bundle was not included in the target block.
+/// A bundle can target a range of blocks, in which case the blocks are
+/// checked one after another until the bundle is found in one of them.
+///
+/// You can `await` the pending bundle. When the bundle is found in one
+/// of the target blocks, or the last target block has been included in
+/// the chain, the future will resolve, either with the bundle hash
+/// indicating that the bundle was included, or with an error indicating
+/// that the bundle was not included in any of the target blocks.
+///
+/// By default the target blocks are polled on an interval. If a
+/// [`BlockFeed`] is attached (see [`PendingBundle::block_feed`]), the target
+/// blocks are instead fetched as soon as they arrive on the feed, and polling
+/// is only used as a fallback.
 ///
 /// To figure out why your bundle was not included, refer to the
 /// [Flashbots documentation][fb_debug].
@@ -30,46 +39,104 @@ use thiserror::Error;
 pub struct PendingBundle<'a, P> {
     bundle_hash: BundleHash,
     block: U64,
+    last_block: U64,
     transactions: Vec<TxHash>,
+    submissions: Vec<RelaySubmission>,
     provider: &'a Provider<P>,
     state: PendingBundleState<'a>,
     interval: Box<dyn Stream<Item = ()> + Send + Unpin>,
+    feed: Option<BlockFeed>,
+    received_at: Option<Instant>,
 }
 
 impl<'a, P: JsonRpcClient> PendingBundle<'a, P> {
     pub fn new(
         bundle_hash: BundleHash,
         block: U64,
+        last_block: U64,
         transactions: Vec<TxHash>,
+        submissions: Vec<RelaySubmission>,
         provider: &'a Provider<P>,
     ) -> Self {
         Self {
             bundle_hash,
             block,
+            last_block,
             transactions,
+            submissions,
             provider,
             state: PendingBundleState::PausedGettingBlock,
             interval: Box::new(interval(DEFAULT_POLL_INTERVAL)),
+            feed: None,
+            received_at: None,
         }
     }
 
+    /// Drive the pending bundle from a block feed instead of polling.
+    pub fn block_feed(mut self, feed: BlockFeed) -> Self {
+        self.feed = Some(feed);
+        self
+    }
+
     /// Get the bundle hash for this pending bundle.
     pub fn bundle_hash(&self) -> BundleHash {
         self.bundle_hash
     }
+
+    /// Get the last block the bundle targets.
+    pub fn last_block(&self) -> U64 {
+        self.last_block
+    }
+
+    /// Get the outcome of the submission to each relay.
+    pub fn submissions(&self) -> &[RelaySubmission] {
+        &self.submissions
+    }
 }
 
 impl<'a, P: JsonRpcClient> Future for PendingBundle<'a, P> {
-    type Output = Result<BundleHash, PendingBundleError>;
+    type Output = Result<IncludedBundle, PendingBundleError>;
 
     fn poll(self: Pin<&mut Self>, ctx: &mut Context) -> Poll<Self::Output> {
         let this = self.project();
 
         match this.state {
-            PendingBundleState::PausedGettingBlock => {
-                futures_util::ready!(this.interval.poll_next_unpin(ctx));
-                let fut = Box::pin(this.provider.get_block(*this.block));
-                *this.state = PendingBundleState::GettingBlock(fut);
+            PendingBundleState::PausedGettingBlock => match this.feed.take() {
+                // If the target block already arrived but could not be fetched,
+                // we fall through and retry on the interval instead.
+                Some(mut feed) if this.received_at.is_none() => {
+                    let latest = *feed.borrow();
+                    match latest {
+                        Some(arrival) if arrival.number >= *this.block => {
+                            *this.feed = Some(feed);
+                            *this.received_at = Some(arrival.received_at);
+                            let fut = Box::pin(this.provider.get_block(*this.block));
+                            *this.state = PendingBundleState::GettingBlock(fut);
+                        }
+                        _ => {
+                            // The feed is moved into the future, so that the
+                            // blocks it has seen are not seen again.
+                            *this.state =
+                                PendingBundleState::WaitingForBlock(Box::pin(async move {
+                                    feed.changed().await.ok()?;
+                                    Some(feed)
+                                }));
+                        }
+                    }
+                    ctx.waker().wake_by_ref();
+                }
+                feed => {
+                    *this.feed = feed;
+                    futures_util::ready!(this.interval.poll_next_unpin(ctx));
+                    let fut = Box::pin(this.provider.get_block(*this.block));
+                    *this.state = PendingBundleState::GettingBlock(fut);
+                    ctx.waker().wake_by_ref();
+                }
+            },
+            PendingBundleState::WaitingForBlock(fut) => {
+                // If the feed was closed, we fall back to polling.
+                *this.feed = futures_util::ready!(fut.as_mut().poll(ctx));
+                *this.state = PendingBundleState::PausedGettingBlock;
                 ctx.waker().wake_by_ref();
             }
             PendingBundleState::GettingBlock(fut) => {
@@ -109,12 +176,26 @@ impl<'a, P: JsonRpcClient> Future for PendingBundle<'a, P> {
                     .iter()
                     .any(|tx_hash| *tx_hash == this.transactions[0]);
 
-                *this.state = PendingBundleState::Completed;
                 if included {
-                    return Poll::Ready(Ok(*this.bundle_hash));
-                } else {
-                    return Poll::Ready(Err(PendingBundleError::BundleNotIncluded));
+                    *this.state = PendingBundleState::Completed;
+                    return Poll::Ready(Ok(IncludedBundle {
+                        bundle_hash: *this.bundle_hash,
+                        block: *this.block,
+                        confirmation_delay: this.received_at.map(|t| t.elapsed()),
+                    }));
+                }
+
+                // Move on to the next target block, if there is one.
+                if *this.block < *this.last_block {
+                    *this.block = *this.block + 1;
+                    *this.received_at = None;
+                    *this.state = PendingBundleState::PausedGettingBlock;
+                    ctx.waker().wake_by_ref();
+                    return Poll::Pending;
                 }
+
+                *this.state = PendingBundleState::Completed;
+                return Poll::Ready(Err(PendingBundleError::BundleNotIncluded));
             }
             PendingBundleState::Completed => {
                 panic!("polled pending bundle future after completion")
@@ -125,10 +206,23 @@ impl<'a, P: JsonRpcClient> Future for PendingBundle<'a, P> {
     }
 }
 
+/// A bundle that was included in one of its target blocks.
+#[derive(Debug, Clone, Copy)]
+pub struct IncludedBundle {
+    /// The bundle hash.
+    pub bundle_hash: BundleHash,
+    /// The block the bundle was included in.
+    pub block: U64,
+    /// How long after the block arrived the inclusion was confirmed.
+    ///
+    /// This is only known when the pending bundle is driven by a block feed.
+    pub confirmation_delay: Option<Duration>,
+}
+
 /// Errors for pending bundles.
 #[derive(Error, Debug)]
 pub enum PendingBundleError {
-    /// The bundle was not included in the target block.
+    /// The bundle was not included in any of the target blocks.
     #[error("Bundle was not included in target block")]
     BundleNotIncluded,
     /// An error occured while interacting with the RPC endpoint.
@@ -142,6 +236,10 @@ enum PendingBundleState<'a> {
     /// Waiting for an interval before calling API again
     PausedGettingBlock,
 
+    /// Waiting for a new block on the block feed, which is handed back once
+    /// a block arrives (or `None` if the feed was closed)
+    WaitingForBlock(Pin<Box<dyn Future<Output = Option<BlockFeed>> + Send + 'a>>),
+
     /// Polling the blockchain to get block information
     GettingBlock(PinBoxFut<'a, Option<Block<TxHash>>>),
 
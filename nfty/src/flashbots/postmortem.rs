@@ -0,0 +1,125 @@
+use ethers_core::types::{TxHash, U256};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Why a bundle was not included in its target block.
+///
+/// See [`FlashbotsMiddleware::explain_miss`](crate::flashbots::FlashbotsMiddleware::explain_miss).
+#[derive(Debug, Clone, PartialEq)]
+pub enum MissReason {
+    /// The nonce of one of the bundle transactions was used by another
+    /// transaction.
+    NonceConsumed { transaction: TxHash, nonce: U256 },
+    /// One of the bundle transactions reverts on top of the parent of the
+    /// target block.
+    Reverted { transaction: TxHash, error: String },
+    /// The bundle could not be simulated on top of the parent of the
+    /// target block.
+    SimulationFailed(String),
+    /// The bundle pays less per gas than any transaction in the target block.
+    Underpriced {
+        effective_gas_price: U256,
+        block_minimum: U256,
+    },
+    /// The relay never simulated the bundle.
+    NotSimulated,
+    /// The relay simulated the bundle, but no builder ever considered it.
+    NeverSeenByBuilder,
+    /// Builders considered the bundle, but sealed blocks without it.
+    Outbid,
+    /// None of the checks found a reason.
+    Unknown,
+}
+
+impl fmt::Display for MissReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MissReason::NonceConsumed { transaction, nonce } => write!(
+                f,
+                "nonce {} of tx 0x{:x} was consumed by another transaction",
+                nonce, transaction
+            ),
+            MissReason::Reverted { transaction, error } => {
+                write!(f, "tx 0x{:x} reverted: {}", transaction, error)
+            }
+            MissReason::SimulationFailed(e) => write!(f, "bundle failed to simulate: {}", e),
+            MissReason::Underpriced {
+                effective_gas_price,
+                block_minimum,
+            } => write!(
+                f,
+                "underpriced, paid {} gwei per gas but the block minimum was {} gwei",
+                effective_gas_price.as_u128() as f64 / 1e9,
+                block_minimum.as_u128() as f64 / 1e9
+            ),
+            MissReason::NotSimulated => write!(f, "the relay never simulated the bundle"),
+            MissReason::NeverSeenByBuilder => write!(f, "the bundle was never seen by a builder"),
+            MissReason::Outbid => write!(
+                f,
+                "builders considered the bundle but chose a more profitable one"
+            ),
+            MissReason::Unknown => write!(f, "unknown"),
+        }
+    }
+}
+
+/// The fees paid by the transactions in a block.
+// `Serialize` is required by `Provider::request`.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct BlockFees {
+    base_fee_per_gas: Option<U256>,
+    transactions: Vec<TransactionFees>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TransactionFees {
+    gas_price: Option<U256>,
+    max_fee_per_gas: Option<U256>,
+    max_priority_fee_per_gas: Option<U256>,
+}
+
+impl BlockFees {
+    /// The lowest priority fee per gas paid by a transaction in the block.
+    ///
+    /// Transactions without a priority fee are ignored, since they usually
+    /// pay the builder directly, which is not visible in the block.
+    pub(crate) fn minimum_priority_fee(&self) -> Option<U256> {
+        let base_fee = self.base_fee_per_gas.unwrap_or_default();
+        self.transactions
+            .iter()
+            .filter_map(
+                |tx| match (tx.max_fee_per_gas, tx.max_priority_fee_per_gas) {
+                    (Some(max_fee), Some(priority_fee)) => {
+                        Some(priority_fee.min(max_fee.saturating_sub(base_fee)))
+                    }
+                    _ => tx
+                        .gas_price
+                        .map(|gas_price| gas_price.saturating_sub(base_fee)),
+                },
+            )
+            .filter(|priority_fee| !priority_fee.is_zero())
+            .min()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn block_minimum_priority_fee() {
+        let fees: BlockFees = serde_json::from_value(serde_json::json!({
+            "baseFeePerGas": "0x64",
+            "transactions": [
+                { "gasPrice": "0x64", "maxFeePerGas": "0x64", "maxPriorityFeePerGas": "0x0" },
+                { "gasPrice": "0x6e", "maxFeePerGas": "0xc8", "maxPriorityFeePerGas": "0xa" },
+                { "gasPrice": "0x69" },
+            ]
+        }))
+        .unwrap();
+
+        assert_eq!(fees.minimum_priority_fee(), Some(U256::from(5)));
+    }
+}
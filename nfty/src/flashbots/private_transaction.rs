@@ -0,0 +1,19 @@
+use crate::flashbots::relay::RelaySubmission;
+use ethers_core::types::{TxHash, U64};
+
+/// A transaction that has been sent privately to the relays.
+///
+/// Unlike a bundle, a private transaction is not bound to a target block.
+/// The relays keep forwarding it to builders until it is included, it is
+/// cancelled (see
+/// [`FlashbotsMiddleware::cancel_private_transaction`](crate::flashbots::FlashbotsMiddleware::cancel_private_transaction)),
+/// or the maximum block number is reached.
+#[derive(Debug, Clone)]
+pub struct PrivateTransaction {
+    /// The transaction hash.
+    pub hash: TxHash,
+    /// The last block the transaction may be included in (if any).
+    pub max_block_number: Option<U64>,
+    /// The outcome of the submission to each relay.
+    pub submissions: Vec<RelaySubmission>,
+}
@@ -1,32 +1,86 @@
 use crate::flashbots::{
     bundle::BundleHash,
-    jsonrpc::{JsonRpcError, Request, Response},
+    health::{RelayFailure, RelayHealth},
+    jsonrpc::{self, JsonRpcError, Request, Response},
 };
 use ethers_core::{
-    types::{H256, U64},
+    types::{Bytes, TxHash, H256, U64},
     utils::keccak256,
 };
 use ethers_signers::Signer;
-use reqwest::{Client, Error as ReqwestError};
+use reqwest::{header::CONTENT_TYPE, Client, Error as ReqwestError};
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
-use std::sync::atomic::{AtomicU64, Ordering};
+use serde_json::Value;
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
 use thiserror::Error;
 use url::Url;
+use uuid::Uuid;
+
+/// How requests to a relay are authenticated.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RelayAuth {
+    /// Requests are not authenticated.
+    None,
+    /// An API key is sent in a header.
+    ApiKey { header: String, key: String },
+    /// The request is signed by the signer of the relay, and the signature
+    /// is sent in a header, along with the signer address.
+    Signature {
+        header: String,
+        payload: SignaturePayload,
+    },
+}
+
+/// What is signed to authenticate a request.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SignaturePayload {
+    /// The hex encoded keccak256 hash of the request body.
+    BodyHash,
+    /// The request body itself.
+    Body,
+}
+
+impl RelayAuth {
+    /// The authentication expected by Flashbots relays, i.e. a signature of
+    /// the hash of the request body in the `X-Flashbots-Signature` header.
+    pub fn flashbots() -> Self {
+        RelayAuth::Signature {
+            header: "X-Flashbots-Signature".into(),
+            payload: SignaturePayload::BodyHash,
+        }
+    }
+}
+
+impl Default for RelayAuth {
+    fn default() -> Self {
+        Self::flashbots()
+    }
+}
 
 /// A Flashbots relay client.
 ///
-/// The client automatically signs every request and sets the Flashbots
-/// authorization header appropriately with the given signer.
+/// By default, the client automatically signs every request and sets the
+/// Flashbots authorization header appropriately with the given signer. This
+/// can be changed with [`Relay::set_auth`].
 ///
 /// **Note**: You probably do not want to use this directly, unless
 /// you want to interact directly with the Relay. Most users should use
 /// [`FlashbotsMiddleware`](crate::FlashbotsMiddleware) instead.
 #[derive(Debug)]
 pub struct Relay<S> {
-    id: AtomicU64,
+    id: Arc<AtomicU64>,
     client: Client,
     url: Url,
     signer: S,
+    auth: RelayAuth,
+    methods: Option<Vec<String>>,
+    health: Arc<Mutex<RelayHealth>>,
 }
 
 /// Errors for relay requests.
@@ -59,48 +113,158 @@ impl<S: Signer> Relay<S> {
     /// Initializes a new relay client.
     pub fn new(url: impl Into<Url>, signer: S) -> Self {
         Self {
-            id: AtomicU64::new(0),
+            id: Default::default(),
             client: Client::new(),
             url: url.into(),
             signer,
+            auth: RelayAuth::default(),
+            methods: None,
+            health: Default::default(),
         }
     }
 
+    /// Set the HTTP client used to send requests to the relay, e.g. to set a
+    /// timeout, a proxy or extra headers.
+    pub fn set_client(&mut self, client: Client) -> &mut Self {
+        self.client = client;
+        self
+    }
+
+    /// Set how requests to the relay are authenticated.
+    pub fn set_auth(&mut self, auth: RelayAuth) -> &mut Self {
+        self.auth = auth;
+        self
+    }
+
+    /// Restrict the methods the relay is sent to those in `methods`.
+    ///
+    /// By default, the relay is assumed to support every method.
+    pub fn set_methods(&mut self, methods: Vec<String>) -> &mut Self {
+        self.methods = Some(methods);
+        self
+    }
+
+    /// Whether the relay supports `method`.
+    pub fn supports(&self, method: &str) -> bool {
+        self.methods
+            .as_ref()
+            .map(|methods| methods.iter().any(|m| m == method))
+            .unwrap_or(true)
+    }
+
+    /// Get the URL of the relay.
+    pub fn url(&self) -> &Url {
+        &self.url
+    }
+
+    /// Get the signer used to sign requests to the relay.
+    pub fn signer(&self) -> &S {
+        &self.signer
+    }
+
+    /// Get a snapshot of the health of the relay.
+    pub fn health(&self) -> RelayHealth {
+        self.health.lock().unwrap().clone()
+    }
+
+    /// Record that a bundle accepted by the relay was included.
+    pub fn record_inclusion(&self) {
+        self.health.lock().unwrap().inclusions += 1;
+    }
+
     /// Sends a request with the provided method to the relay, with the
     /// parameters serialized as JSON.
+    ///
+    /// The outcome of every request is recorded in the health of the relay.
     pub async fn request<T: Serialize + Send + Sync, R: DeserializeOwned>(
         &self,
         method: &str,
         params: T,
     ) -> Result<R, RelayError<S>> {
-        let next_id = self.id.load(Ordering::SeqCst) + 1;
-        self.id.store(next_id, Ordering::SeqCst);
-
-        let payload = Request::new(next_id, method, params);
-
-        let signature = self
-            .signer
-            .sign_message(format!(
-                "0x{:x}",
-                H256::from(keccak256(
-                    serde_json::to_string(&payload)
-                        .map_err(RelayError::RequestSerdeJson)?
-                        .as_bytes()
-                ))
-            ))
+        let start = Instant::now();
+        let payload = Request::new(self.next_id(), method, params);
+        let response = self
+            .post::<_, Response<R>>(&payload)
             .await
-            .map_err(RelayError::SignerError)?;
+            .and_then(|res| Ok(res.data.into_result()?));
+        self.record(start, &response);
+
+        response
+    }
+
+    /// Sends several calls to the relay in a single request, as a JSON-RPC
+    /// batch.
+    ///
+    /// The results are returned in the same order as the calls. The request
+    /// only fails as a whole if it could not be sent, or if the relay did not
+    /// respond with a batch.
+    pub async fn batch_request(
+        &self,
+        calls: &[(&str, Value)],
+    ) -> Result<Vec<Result<Value, RelayError<S>>>, RelayError<S>> {
+        let payload = calls
+            .iter()
+            .map(|(method, params)| Request::new(self.next_id(), method, params))
+            .collect::<Vec<_>>();
+        let ids = payload.iter().map(|req| req.id()).collect::<Vec<_>>();
+
+        // Errors of the individual calls are not recorded, since the relay
+        // itself responded.
+        let start = Instant::now();
+        let responses = self.post::<_, Vec<Response<Value>>>(&payload).await;
+        self.record(start, &responses);
+
+        Ok(jsonrpc::match_batch_responses(&ids, responses?)
+            .into_iter()
+            .map(|res| res.map_err(RelayError::JsonRpcError))
+            .collect())
+    }
+
+    /// Records the outcome of a request in the health of the relay.
+    fn record<R>(&self, start: Instant, response: &Result<R, RelayError<S>>) {
+        let failure = response.as_ref().err().map(|e| match e {
+            RelayError::ClientError { .. } => RelayFailure::Client,
+            RelayError::RequestError(_) => RelayFailure::Request,
+            RelayError::JsonRpcError(_) => RelayFailure::JsonRpc,
+            RelayError::ResponseSerdeJson { .. } => RelayFailure::Response,
+            RelayError::RequestSerdeJson(_) | RelayError::SignerError(_) => RelayFailure::Local,
+        });
+        self.health.lock().unwrap().record(start.elapsed(), failure);
+    }
+
+    async fn post<T: Serialize + Send + Sync, R: DeserializeOwned>(
+        &self,
+        payload: &T,
+    ) -> Result<R, RelayError<S>> {
+        let body = serde_json::to_string(payload).map_err(RelayError::RequestSerdeJson)?;
 
-        let res = self
+        let mut req = self
             .client
             .post(self.url.as_ref())
-            .header(
-                "X-Flashbots-Signature",
-                format!("{:?}:0x{}", self.signer.address(), signature),
-            )
-            .json(&payload)
-            .send()
-            .await?;
+            .header(CONTENT_TYPE, "application/json");
+        match &self.auth {
+            RelayAuth::None => {}
+            RelayAuth::ApiKey { header, key } => req = req.header(header.as_str(), key.as_str()),
+            RelayAuth::Signature { header, payload } => {
+                let message = match payload {
+                    SignaturePayload::BodyHash => {
+                        format!("0x{:x}", H256::from(keccak256(body.as_bytes())))
+                    }
+                    SignaturePayload::Body => body.clone(),
+                };
+                let signature = self
+                    .signer
+                    .sign_message(message)
+                    .await
+                    .map_err(RelayError::SignerError)?;
+                req = req.header(
+                    header.as_str(),
+                    format!("{:?}:0x{}", self.signer.address(), signature),
+                );
+            }
+        }
+
+        let res = req.body(body).send().await?;
         let status = res.error_for_status_ref();
 
         match status {
@@ -117,26 +281,58 @@ impl<S: Signer> Relay<S> {
             }
             Ok(_) => {
                 let text = res.text().await?;
-                let res: Response<R> = serde_json::from_str(&text)
-                    .map_err(|err| RelayError::ResponseSerdeJson { err, text })?;
-
-                Ok(res.data.into_result()?)
+                serde_json::from_str(&text)
+                    .map_err(|err| RelayError::ResponseSerdeJson { err, text })
             }
         }
     }
+
+    /// The ids are shared with the clones of the relay, so that they stay
+    /// unique across all of them.
+    fn next_id(&self) -> u64 {
+        self.id.fetch_add(1, Ordering::SeqCst) + 1
+    }
 }
 
 impl<S: Signer + Clone> Clone for Relay<S> {
     fn clone(&self) -> Self {
         Self {
-            id: AtomicU64::new(0),
+            id: self.id.clone(),
             client: self.client.clone(),
             url: self.url.clone(),
             signer: self.signer.clone(),
+            auth: self.auth.clone(),
+            methods: self.methods.clone(),
+            health: self.health.clone(),
         }
     }
 }
 
+/// The outcome of a submission to a single relay.
+///
+/// When a bundle is sent to several relays at once, one of these is
+/// recorded for every relay, whether the relay accepted the bundle or not.
+#[derive(Debug, Clone)]
+pub struct RelaySubmission {
+    /// The relay the submission was sent to.
+    pub relay: Url,
+    /// The hash the relay returned, i.e. the bundle hash for `eth_sendBundle`.
+    ///
+    /// This is `None` if the relay rejected the submission.
+    pub hash: Option<H256>,
+    /// How long the relay took to respond.
+    pub latency: Duration,
+    /// The error returned by the relay (if any).
+    pub error: Option<String>,
+}
+
+impl RelaySubmission {
+    /// Whether the relay accepted the submission.
+    pub fn is_ok(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
 #[derive(Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub(crate) struct SendBundleResponse {
@@ -155,3 +351,23 @@ pub(crate) struct GetBundleStatsParams {
 pub(crate) struct GetUserStatsParams {
     pub(crate) block_number: U64,
 }
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct CancelBundleParams {
+    pub(crate) replacement_uuid: Uuid,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct SendPrivateTransactionParams {
+    pub(crate) tx: Bytes,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) max_block_number: Option<U64>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct CancelPrivateTransactionParams {
+    pub(crate) tx_hash: TxHash,
+}
@@ -0,0 +1,178 @@
+use crate::flashbots::bundle::SimulatedBundle;
+use ethers_core::types::{H256, U256};
+use std::time::Duration;
+use url::Url;
+
+/// The result of simulating a bundle on a single relay.
+#[derive(Debug, Clone)]
+pub struct RelaySimulation {
+    /// The relay the bundle was simulated on.
+    pub relay: Url,
+    /// How long the relay took to respond.
+    pub latency: Duration,
+    /// The simulated bundle, or the error returned by the relay.
+    pub result: Result<SimulatedBundle, String>,
+}
+
+/// A difference between the baseline simulation and the simulation
+/// of another relay.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SimulationDifference {
+    /// The relays disagree on the coinbase difference of the bundle.
+    CoinbaseDiff {
+        relay: Url,
+        expected: U256,
+        actual: U256,
+    },
+    /// The relays disagree on whether (or why) a transaction reverted.
+    TransactionError {
+        relay: Url,
+        transaction: H256,
+        expected: Option<String>,
+        actual: Option<String>,
+    },
+}
+
+/// The simulations of a bundle on every relay that supports `eth_callBundle`,
+/// side by side.
+///
+/// The first successful simulation is used as the baseline that the other
+/// simulations are compared against.
+#[derive(Debug, Clone)]
+pub struct SimulationComparison {
+    pub simulations: Vec<RelaySimulation>,
+}
+
+impl SimulationComparison {
+    /// Get the baseline simulation (if any relay simulated the bundle).
+    pub fn baseline(&self) -> Option<&RelaySimulation> {
+        self.simulations.iter().find(|s| s.result.is_ok())
+    }
+
+    /// Get the differences between the baseline and every other
+    /// successful simulation.
+    pub fn differences(&self) -> Vec<SimulationDifference> {
+        let baseline = match self.baseline() {
+            Some(RelaySimulation {
+                result: Ok(bundle), ..
+            }) => bundle,
+            _ => return Vec::new(),
+        };
+
+        let mut differences = Vec::new();
+        for simulation in self
+            .simulations
+            .iter()
+            .skip_while(|s| s.result.is_err())
+            .skip(1)
+        {
+            let bundle = match simulation.result.as_ref() {
+                Ok(bundle) => bundle,
+                Err(_) => continue,
+            };
+
+            if bundle.coinbase_diff != baseline.coinbase_diff {
+                differences.push(SimulationDifference::CoinbaseDiff {
+                    relay: simulation.relay.clone(),
+                    expected: baseline.coinbase_diff,
+                    actual: bundle.coinbase_diff,
+                });
+            }
+
+            for tx in &bundle.transactions {
+                let expected = baseline
+                    .transactions
+                    .iter()
+                    .find(|b| b.hash == tx.hash)
+                    .and_then(|b| b.error.clone());
+                if expected != tx.error {
+                    differences.push(SimulationDifference::TransactionError {
+                        relay: simulation.relay.clone(),
+                        transaction: tx.hash,
+                        expected,
+                        actual: tx.error.clone(),
+                    });
+                }
+            }
+        }
+
+        differences
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn simulated_bundle(coinbase_diff: &str, error: Option<&str>) -> SimulatedBundle {
+        serde_json::from_value(serde_json::json!({
+            "bundleGasPrice": "1",
+            "bundleHash": "0x73b1e258c7a42fd0230b2fd05529c5d4b6fcb66c227783f8bece8aeacdd1db2e",
+            "coinbaseDiff": coinbase_diff,
+            "ethSentToCoinbase": "0",
+            "gasFees": coinbase_diff,
+            "results": [{
+                "coinbaseDiff": coinbase_diff,
+                "ethSentToCoinbase": "0",
+                "fromAddress": "0x02A727155aeF8609c9f7F2179b2a1f560B39F5A0",
+                "gasFees": coinbase_diff,
+                "gasPrice": "1",
+                "gasUsed": 21000,
+                "toAddress": "0x73625f59CAdc5009Cb458B751b3E7b6b48C06f2C",
+                "txHash": "0x669b4704a7d993a946cdd6e2f95233f308ce0c4649d2e04944e8299efcaa098a",
+                "value": "0x",
+                "error": error,
+            }],
+            "stateBlockNumber": 1,
+            "totalGasUsed": 21000
+        }))
+        .unwrap()
+    }
+
+    fn relay_simulation(url: &str, result: Result<SimulatedBundle, String>) -> RelaySimulation {
+        RelaySimulation {
+            relay: Url::parse(url).unwrap(),
+            latency: Duration::from_millis(0),
+            result,
+        }
+    }
+
+    #[test]
+    fn compare_simulations() {
+        let comparison = SimulationComparison {
+            simulations: vec![
+                relay_simulation("https://a.example", Err("method not found".into())),
+                relay_simulation("https://b.example", Ok(simulated_bundle("21000", None))),
+                relay_simulation("https://c.example", Ok(simulated_bundle("21000", None))),
+                relay_simulation(
+                    "https://d.example",
+                    Ok(simulated_bundle("42000", Some("execution reverted"))),
+                ),
+            ],
+        };
+
+        assert_eq!(
+            comparison.baseline().unwrap().relay.as_str(),
+            "https://b.example/"
+        );
+
+        let differences = comparison.differences();
+        assert_eq!(differences.len(), 2);
+        assert_eq!(
+            differences[0],
+            SimulationDifference::CoinbaseDiff {
+                relay: Url::parse("https://d.example").unwrap(),
+                expected: U256::from(21000),
+                actual: U256::from(42000),
+            }
+        );
+        assert!(matches!(
+            &differences[1],
+            SimulationDifference::TransactionError {
+                expected: None,
+                actual: Some(_),
+                ..
+            }
+        ));
+    }
+}
@@ -0,0 +1,174 @@
+use ethers_core::{
+    types::{Address, Bytes, Signature, TxHash, U256},
+    utils::keccak256,
+};
+use rlp::{Decodable, Rlp, RlpStream};
+
+/// The fields of an RLP encoded signed transaction, along with its
+/// recovered sender.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct DecodedTransaction {
+    pub(crate) hash: TxHash,
+    pub(crate) from: Address,
+    pub(crate) to: Option<Address>,
+    pub(crate) nonce: U256,
+    pub(crate) gas: U256,
+    pub(crate) value: U256,
+    pub(crate) input: Bytes,
+    /// The gas price of legacy transactions, or the max fee per gas of
+    /// EIP-1559 transactions.
+    pub(crate) max_fee_per_gas: U256,
+    /// The max priority fee per gas of EIP-1559 transactions.
+    pub(crate) max_priority_fee_per_gas: Option<U256>,
+}
+
+impl DecodedTransaction {
+    /// The priority fee per gas the transaction pays on top of `base_fee`.
+    pub(crate) fn priority_fee(&self, base_fee: U256) -> U256 {
+        let priority_fee = self.max_fee_per_gas.saturating_sub(base_fee);
+        match self.max_priority_fee_per_gas {
+            Some(max_priority_fee) => priority_fee.min(max_priority_fee),
+            None => priority_fee,
+        }
+    }
+}
+
+/// Decodes an RLP encoded signed legacy, EIP-2930 or EIP-1559 transaction
+/// and recovers its sender.
+///
+/// Returns `None` if the transaction is malformed or of an unknown type.
+pub(crate) fn decode_raw_transaction(raw: &[u8]) -> Option<DecodedTransaction> {
+    let first = *raw.first()?;
+
+    // Typed transactions are prefixed with their type, followed by the RLP
+    // encoded fields. The signature is always in the last three fields.
+    let (tx_type, payload, fields) = match first {
+        0x01 => (Some(first), &raw[1..], 8),
+        0x02 => (Some(first), &raw[1..], 9),
+        0xc0..=0xff => (None, raw, 6),
+        _ => return None,
+    };
+
+    let rlp = Rlp::new(payload);
+    if rlp.item_count().ok()? != fields + 3 {
+        return None;
+    }
+
+    // Every field after the nonce is shifted by the chain id in typed
+    // transactions, and by the extra fee field in EIP-1559 transactions.
+    let offset = if tx_type.is_some() { 1 } else { 0 };
+    let fee_offset = if first == 0x02 { offset + 1 } else { offset };
+    let max_priority_fee_per_gas = match first {
+        0x02 => Some(rlp.val_at(2).ok()?),
+        _ => None,
+    };
+
+    let v: u64 = rlp.val_at(fields).ok()?;
+    let signature = Signature {
+        r: rlp.val_at(fields + 1).ok()?,
+        s: rlp.val_at(fields + 2).ok()?,
+        v,
+    };
+
+    let replay_protected = tx_type.is_none() && v >= 35;
+    let mut stream = RlpStream::new_list(if replay_protected { fields + 3 } else { fields });
+    for i in 0..fields {
+        stream.append_raw(rlp.at(i).ok()?.as_raw(), 1);
+    }
+    if replay_protected {
+        // EIP-155
+        stream.append(&((v - 35) / 2));
+        stream.append(&0u8);
+        stream.append(&0u8);
+    }
+
+    let mut sighash_payload = tx_type.map(|t| vec![t]).unwrap_or_default();
+    sighash_payload.extend_from_slice(&stream.out());
+
+    let to = rlp.at(fee_offset + 3).ok()?;
+    Some(DecodedTransaction {
+        hash: keccak256(raw).into(),
+        from: signature
+            .recover(TxHash::from(keccak256(&sighash_payload)))
+            .ok()?,
+        to: if to.is_empty() {
+            None
+        } else {
+            Some(Address::decode(&to).ok()?)
+        },
+        nonce: rlp.val_at(offset).ok()?,
+        gas: rlp.val_at(fee_offset + 2).ok()?,
+        value: rlp.val_at(fee_offset + 4).ok()?,
+        input: rlp.val_at::<Vec<u8>>(fee_offset + 5).ok()?.into(),
+        max_fee_per_gas: rlp.val_at(fee_offset + 1).ok()?,
+        max_priority_fee_per_gas,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers_core::types::{
+        transaction::eip2718::TypedTransaction, Eip1559TransactionRequest, TransactionRequest,
+    };
+    use ethers_signers::{LocalWallet, Signer};
+
+    #[tokio::test]
+    async fn decode_signed_transactions() {
+        let wallet: LocalWallet =
+            "380eb0f3d505f087e438eca80bc4df9a7faa24f868e69fc0440261a0fc0567dc"
+                .parse::<LocalWallet>()
+                .unwrap()
+                .with_chain_id(1u64);
+
+        let transactions = vec![
+            (
+                TypedTransaction::Eip1559(
+                    Eip1559TransactionRequest::new()
+                        .to(Address::zero())
+                        .value(1)
+                        .data(vec![0x12, 0x34])
+                        .nonce(7)
+                        .gas(21000)
+                        .max_fee_per_gas(100)
+                        .max_priority_fee_per_gas(2),
+                ),
+                Some(U256::from(2)),
+            ),
+            (
+                TypedTransaction::Legacy(
+                    TransactionRequest::new()
+                        .to(Address::zero())
+                        .value(1)
+                        .data(vec![0x12, 0x34])
+                        .nonce(7)
+                        .gas(21000)
+                        .gas_price(100),
+                ),
+                None,
+            ),
+        ];
+
+        for (tx, max_priority_fee_per_gas) in transactions {
+            let signature = wallet.sign_transaction(&tx).await.unwrap();
+            let raw = tx.rlp_signed(wallet.chain_id(), &signature);
+
+            assert_eq!(
+                decode_raw_transaction(&raw),
+                Some(DecodedTransaction {
+                    hash: keccak256(&raw).into(),
+                    from: wallet.address(),
+                    to: Some(Address::zero()),
+                    nonce: 7.into(),
+                    gas: 21000.into(),
+                    value: 1.into(),
+                    input: vec![0x12, 0x34].into(),
+                    max_fee_per_gas: 100.into(),
+                    max_priority_fee_per_gas,
+                })
+            );
+        }
+
+        assert_eq!(decode_raw_transaction(&[0x02, 0xc0]), None);
+    }
+}
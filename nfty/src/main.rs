@@ -18,15 +18,22 @@ pub use context::*;
 pub mod util;
 
 mod context;
+mod fees;
 pub mod flashbots;
 mod looksrare;
 mod mint;
 mod model;
 mod opensea;
+mod planner;
+mod results;
 mod themida;
+mod tracker;
 
 pub type Error = Box<dyn StdError + Send + Sync>;
 
+/// How often the health of every relay is logged.
+const RELAY_HEALTH_INTERVAL: Duration = Duration::from_secs(300);
+
 #[derive(FromArgs)]
 /// opensea goes brrrr
 struct App {
@@ -287,23 +294,34 @@ async fn main() -> Result<(), Error> {
             Some(signer) if !signer.is_empty() => Wallet::from_str(signer)?,
             _ => wallet.clone(),
         };
-        let provider = SignerMiddleware::new(
-            FlashbotsMiddleware::new(
-                base_provider,
-                config
-                    .global
-                    .relays
-                    .iter()
-                    .map(|u| Url::parse(u).unwrap())
-                    .collect(),
-                flashbots_signer,
-            ),
-            wallet,
-        );
+        let pubsub = base_provider.clone();
+        let block_feed = flashbots::spawn_block_feed(base_provider.clone());
+        let relays = config
+            .global
+            .relays
+            .iter()
+            .map(|relay| util::relay(&relay.into(), flashbots_signer.clone()))
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut flashbots = FlashbotsMiddleware::with_relays(base_provider, relays);
+        flashbots.set_block_feed(block_feed);
+        if let Some(simulation_relay) = config.global.simulation_relay.as_ref() {
+            if !simulation_relay.is_empty() {
+                flashbots.set_simulation_relay(Url::parse(simulation_relay)?);
+            }
+        }
+        if let Some(simulation_provider) = config.global.simulation_provider.as_ref() {
+            if !simulation_provider.is_empty() {
+                flashbots.set_simulation_provider(Provider::<Http>::try_from(
+                    simulation_provider.as_str(),
+                )?);
+            }
+        }
+        let provider = SignerMiddleware::new(flashbots, wallet);
 
         let ctx = Context::new(
             config,
             provider,
+            pubsub,
             autosolve.clone(),
             credentials.clone(),
             session_id.clone(),
@@ -345,6 +363,17 @@ async fn launch<M: StaticMiddleware + Clone, S: StaticSigner>(
         warn!("Missing flashbots data")
     }
 
+    let health_ctx = ctx.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(RELAY_HEALTH_INTERVAL);
+        // the first tick completes immediately, when nothing has been sent yet
+        interval.tick().await;
+        loop {
+            interval.tick().await;
+            health_ctx.log_relay_health();
+        }
+    });
+
     #[cfg(feature = "themida")]
     unsafe {
         crate::themida::VM_DOLPHIN_BLACK_START()
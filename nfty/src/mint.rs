@@ -1,6 +1,12 @@
 #![allow(dead_code, unused_imports)]
 
-use crate::{flashbots::BundleRequest, Context, Error};
+use crate::{
+    fees::FeeStrategy,
+    flashbots::{decode_raw_transaction, BundleRequest},
+    planner, results,
+    tracker::TransactionTracker,
+    util, Context, Error, DEFAULT_PRIVATE_TX_BLOCKS,
+};
 use autosolve::types::CaptchaTokenRequest;
 use chrono::{Duration, NaiveDateTime, Utc};
 use deno_core::{error::AnyError, Extension, FsModuleLoader, OpState};
@@ -11,14 +17,22 @@ use deno_runtime::{
     worker::{MainWorker, WorkerOptions},
     BootstrapOptions,
 };
-use ethers::prelude::{transaction::eip2718::TypedTransaction, *};
-use ethers_core::abi::ParamType;
+use ethers::prelude::{
+    transaction::{eip2718::TypedTransaction, eip2930::AccessList},
+    *,
+};
+use ethers_core::{
+    abi::{Abi, ParamType},
+    utils::keccak256,
+};
 use itertools::Itertools;
 use log::*;
 use serde::Deserialize;
 use shared::{
-    config::{IncludeAddressType, Mint, MintArgument, MintMode},
+    abi, checks,
+    config::{EventTrigger, IncludeAddressType, Mint, MintArgument, MintMode, StateChecks},
     contracts,
+    token::Placeholders,
 };
 use std::{
     cell::RefCell,
@@ -31,6 +45,14 @@ use tokio::{
     sync::{oneshot, Mutex},
     task::LocalSet,
 };
+use uuid::Uuid;
+
+/// The gas limit of the transaction simulated to discover the gas used by
+/// Flashbots mints.
+const TEMPLATE_GAS_LIMIT: u64 = 1_000_000;
+/// The safety margin added to discovered gas limits, as a fraction of the
+/// gas used in simulation.
+const DEFAULT_GAS_MARGIN: f64 = 0.2;
 
 #[derive(Debug, Deserialize)]
 struct MintInfo {
@@ -94,91 +116,96 @@ pub async fn handle<M: 'static + Middleware + Clone, S: 'static + Signer + Clone
     our_addr: Address,
 ) -> Result<(), shared::Error> {
     let mint_config = ctx.config().mint.as_ref().expect("expected Mint config");
+    let wallets = util::mint_wallets(
+        &ctx.config().account.private_key,
+        mint_config,
+        ctx.provider().signer().chain_id(),
+    )
+    .await?;
+    if wallets.len() > 1 {
+        info!("minting with {} wallets", wallets.len());
+    }
+    let fee_strategy = FeeStrategy::for_task(
+        ctx.config(),
+        mint_config.fee_strategy.as_ref(),
+        mint_config.gas_fee,
+        mint_config.priority_fee,
+    )?;
     let included_txs = Arc::new(Mutex::new(HashMap::new()));
-
-    let pool_monitor_active = if mint_config.mode == MintMode::Flashbots {
-        let pool_ctx = ctx.clone();
-        let pool_mint_config = mint_config.clone();
-        let pool_included_txs = included_txs.clone();
-
-        let include_type = pool_mint_config
-            .include_address_type
-            .clone()
-            .unwrap_or(IncludeAddressType::From);
-        let include_address = pool_mint_config
-            .include_address
+    // the ABIs of the config are loaded once for the task
+    let mint_abi = abi::load_optional(mint_config.abi_path.as_ref())?;
+    let price_abi = abi::load_optional(
+        mint_config
+            .price_function
             .as_ref()
-            .and_then(|a| Address::from_str(a).ok());
-        let include_method = if let Some(sig) = pool_mint_config.include_method {
-            if sig.is_empty() {
-                None
-            } else if sig.starts_with("0x") {
-                Some(shared::util::decode_hex(&sig)?)
-            } else {
-                Some(shared::contracts::function_identifier(sig).to_vec())
-            }
-        } else {
-            None
-        };
+            .and_then(|price_function| price_function.abi_path.as_ref()),
+    )?;
+    let state_check_abis = mint_config
+        .state_checks
+        .iter()
+        .flatten()
+        .map(|check| abi::load_optional(check.abi_path.as_ref()))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let include_filter = IncludeFilter::new(mint_config)?;
+
+    let pool_monitor_active = match include_filter.clone() {
+        Some(include_filter) if mint_config.mode == MintMode::Flashbots => {
+            let pool_ctx = ctx.clone();
+            let pool_included_txs = included_txs.clone();
 
-        if include_address.is_none() && include_method.is_none() {
-            false
-        } else {
             tokio::spawn(async move {
                 info!("starting tx pool monitor");
-                loop {
-                    info!("fetching tx pool");
-                    let mut matching_txs = HashMap::new();
-                    let txpool = pool_ctx.provider().txpool_content().await.unwrap();
-                    let pending_txs = txpool
-                        .pending
-                        .values()
-                        .map(|x| x.values().collect_vec())
-                        .concat();
-                    let queued_txs = txpool
-                        .queued
-                        .values()
-                        .map(|x| x.values().collect_vec())
-                        .concat();
-                    for tx in queued_txs.into_iter().chain(pending_txs.into_iter()) {
-                        if let Some(include_address) = include_address {
-                            match include_type {
-                                IncludeAddressType::From => {
-                                    if tx.from != include_address {
-                                        continue;
-                                    }
-                                }
-                                IncludeAddressType::To => {
-                                    if tx.to != Some(include_address) {
-                                        continue;
-                                    }
-                                }
-                            }
-                        }
-
-                        if let Some(include_method) = include_method.clone() {
-                            if !tx.input.0.starts_with(&include_method) {
-                                continue;
-                            }
+                let mut pending_txs = match pool_ctx.pubsub().subscribe_pending_txs().await {
+                    Ok(pending_txs) => pending_txs,
+                    Err(e) => {
+                        error!("error subscribing to pending txs: {}", e);
+                        return;
+                    }
+                };
+                while let Some(tx_hash) = pending_txs.next().await {
+                    if let Ok(Some(tx)) = pool_ctx.pubsub().get_transaction(tx_hash).await {
+                        if include_filter.matches(&tx) {
+                            info!("found matching include tx: 0x{:x}", tx.hash);
+                            pool_included_txs.lock().await.insert(tx.hash, tx);
                         }
-
-                        info!("found matching include tx: 0x{:x}", tx.hash);
-                        matching_txs.insert(tx.hash, tx.rlp());
                     }
-
-                    *pool_included_txs.lock().await = matching_txs;
-                    info!("updated tx pool");
                 }
+                error!("pending tx subscription ended");
             });
 
             true
         }
-    } else {
-        false
+        _ => false,
     };
 
-    if mint_config.mode == MintMode::Normal {
-        if let Some(start_time) = mint_config.start_time {
+    // in Normal mode, the mint backruns the matching tx in the same block
+    let mut backrun_fees = None;
+    if mint_config.mode == MintMode::Normal && include_filter.is_some() {
+        // gas estimation fails until the matching tx is mined, which is too
+        // late to land in the same block
+        if mint_config.gas_limit.is_none() {
+            return Err("backrunning requires a gas_limit".into());
+        }
+
+        let target = wait_for_include_tx(&ctx, include_filter.as_ref().unwrap()).await?;
+        let fees = decode_raw_transaction(&target.rlp())
+            .map(|target| {
+                let max_fee = target.max_fee_per_gas;
+                (max_fee, target.max_priority_fee_per_gas.unwrap_or(max_fee))
+            })
+            .ok_or("could not decode matching tx")?;
+        info!(
+            "backrunning tx 0x{:x} with max fee {} gwei and priority fee {} gwei",
+            target.hash,
+            fees.0.as_u128() as f64 / 1e9,
+            fees.1.as_u128() as f64 / 1e9
+        );
+        backrun_fees = Some(fees);
+    } else if mint_config.mode != MintMode::Flashbots {
+        if let Some(trigger) = mint_config.trigger.as_ref() {
+            wait_for_event(&ctx, mint_config, trigger).await?;
+        } else if let Some(start_time) = mint_config.start_time {
             if start_time > 0 {
                 info!("sleeping until 1s before start time...");
                 let until_drop =
@@ -189,130 +216,93 @@ pub async fn handle<M: 'static + Middleware + Clone, S: 'static + Signer + Clone
 
         if let Some(state_checks) = mint_config.state_checks.as_ref() {
             if !state_checks.is_empty() {
-                let mut sale_started = true;
-                for check in state_checks {
-                    let resp = ctx
-                        .provider()
-                        .call(
-                            &TypedTransaction::Eip1559(Eip1559TransactionRequest {
-                                to: Some(NameOrAddress::Address(Address::from_str(
-                                    check
-                                        .address
-                                        .as_ref()
-                                        .unwrap_or(&mint_config.contract_address),
-                                )?)),
-                                data: Some(
-                                    contracts::encode_call(
-                                        &check.function,
-                                        check
-                                            .arguments
-                                            .iter()
-                                            .map(|x| x.r#type.to_token(&x.value).unwrap())
-                                            .collect::<Vec<_>>()
-                                            .as_slice(),
-                                    )
-                                    .into(),
-                                ),
-                                ..Default::default()
-                            }),
-                            None,
-                        )
-                        .await?;
-
-                    let expected_response = contracts::encode_args(
-                        check
-                            .return_value
-                            .iter()
-                            .map(|x| x.r#type.to_token(&x.value).unwrap())
-                            .collect::<Vec<_>>()
-                            .as_slice(),
-                    )
-                    .to_vec();
-
-                    if resp.to_vec() != expected_response {
-                        sale_started = false;
-                    }
-                }
-
-                if sale_started {
+                if state_checks_pass(&ctx, mint_config, state_checks, &state_check_abis).await? {
                     info!("sale has started, sending txs...");
                 } else {
                     info!("waiting for sale start...");
                     let mut block_sub = ctx.provider().watch_blocks().await.unwrap();
-                    'state_check: loop {
+                    loop {
                         info!("waiting for next block...");
                         let block = block_sub.next().await;
                         if block.is_none() {
                             return Err("error watching blocks".into());
                         }
 
-                        for check in state_checks {
-                            let resp = ctx
-                                .provider()
-                                .call(
-                                    &TypedTransaction::Eip1559(Eip1559TransactionRequest {
-                                        to: Some(NameOrAddress::Address(Address::from_str(
-                                            check
-                                                .address
-                                                .as_ref()
-                                                .unwrap_or(&mint_config.contract_address),
-                                        )?)),
-                                        data: Some(
-                                            contracts::encode_call(
-                                                &check.function,
-                                                check
-                                                    .arguments
-                                                    .iter()
-                                                    .map(|x| x.r#type.to_token(&x.value).unwrap())
-                                                    .collect::<Vec<_>>()
-                                                    .as_slice(),
-                                            )
-                                            .into(),
-                                        ),
-                                        ..Default::default()
-                                    }),
-                                    None,
-                                )
-                                .await?;
-
-                            let expected_response = contracts::encode_args(
-                                check
-                                    .return_value
-                                    .iter()
-                                    .map(|x| x.r#type.to_token(&x.value).unwrap())
-                                    .collect::<Vec<_>>()
-                                    .as_slice(),
-                            )
-                            .to_vec();
-
-                            if resp.to_vec() != expected_response {
-                                warn!("sale is not live");
-                                continue 'state_check;
-                            }
+                        if state_checks_pass(&ctx, mint_config, state_checks, &state_check_abis)
+                            .await?
+                        {
+                            break;
                         }
-
-                        break 'state_check;
+                        warn!("sale is not live");
                     }
                 }
             }
         }
     }
 
-    let calldata = generate_calldata(ctx.clone(), mint_config).await;
+    let calldata = planner::MintCalldata::new(
+        generate_calldata(ctx.clone(), mint_config, mint_abi.as_ref()).await?,
+        mint_abi,
+    );
+    // rebuilt bundles replace the previous one at the relays
+    let replacement_uuid = Uuid::new_v4();
+    let target_blocks = mint_config
+        .bundle_window
+        .as_ref()
+        .and_then(|w| w.blocks)
+        .unwrap_or(1)
+        .max(1);
+    let validity = mint_config
+        .bundle_window
+        .as_ref()
+        .and_then(|w| w.validity)
+        .unwrap_or(Duration::hours(8).num_seconds() as u64);
+    // the gas limit of Flashbots mints, once it is discovered by simulation
+    let mut discovered_gas_limit = None;
+    let coinbase_payment = match mint_config.coinbase_payment.as_ref() {
+        Some(payment_config) => util::coinbase_payment(payment_config)?.map(|payment| {
+            (
+                payment,
+                util::coinbase_payment_value(payment_config, U256::zero()),
+            )
+        }),
+        None => None,
+    };
 
     loop {
         match mint_config.mode {
             MintMode::Flashbots => loop {
-                let nonce = ctx.provider().get_transaction_count(our_addr, None).await?;
+                let nonces = wallet_nonces(&ctx, &wallets).await?;
+                let nonce = nonces[&our_addr];
 
                 let mut bundles = Vec::new();
 
                 if pool_monitor_active {
-                    let included_txs = { included_txs.lock().await.clone() };
+                    let included_txs = {
+                        let mut included_txs = included_txs.lock().await;
+                        // matching txs whose nonce was used, by the tx itself
+                        // or by a replacement, can no longer be included
+                        let mut sender_nonces = HashMap::new();
+                        for (tx_hash, tx) in included_txs.clone() {
+                            let sender_nonce = match sender_nonces.get(&tx.from) {
+                                Some(nonce) => *nonce,
+                                None => {
+                                    let nonce =
+                                        ctx.provider().get_transaction_count(tx.from, None).await?;
+                                    sender_nonces.insert(tx.from, nonce);
+                                    nonce
+                                }
+                            };
+                            if tx.nonce < sender_nonce {
+                                included_txs.remove(&tx_hash);
+                            }
+                        }
+                        included_txs.clone()
+                    };
                     if !included_txs.is_empty() {
-                        for raw_tx in included_txs.values().cloned() {
+                        for tx in included_txs.values() {
                             let mut bundle = BundleRequest::new();
-                            bundle.push_transaction(raw_tx);
+                            bundle.push_transaction(tx.rlp());
                             bundles.push(bundle);
                         }
                     } else {
@@ -322,102 +312,137 @@ pub async fn handle<M: 'static + Middleware + Clone, S: 'static + Signer + Clone
                     bundles.push(BundleRequest::new());
                 }
 
-                let gas_fee = U256::from((mint_config.gas_fee * 1e9) as u128);
-                let total_txs = mint_config.transaction_count.unwrap_or(1);
+                let fees = fee_strategy.fees(ctx.provider(), target_blocks).await?;
+                let gas_fee = fees.max_fee_per_gas;
+                let plans = planner::plan(&ctx, mint_config, &wallets).await?;
+                if plans.values().all(|plan| plan.transactions == 0) {
+                    info!("nothing left to mint, exiting.");
+                    return Ok(());
+                }
 
                 let block_number = ctx.provider().get_block_number().await?;
                 let target_block = block_number + 1;
 
-                let value = if let Some(price_function) = mint_config.price_function.as_ref() {
-                    let resp = ctx
-                        .provider()
-                        .call(
-                            &TypedTransaction::Eip1559(Eip1559TransactionRequest {
-                                to: Some(NameOrAddress::Address(Address::from_str(
-                                    price_function
-                                        .address
-                                        .as_ref()
-                                        .unwrap_or(&mint_config.contract_address),
-                                )?)),
-                                data: Some(
-                                    contracts::encode_call(
-                                        &price_function.function,
-                                        price_function
-                                            .arguments
-                                            .iter()
-                                            .map(|x| x.r#type.to_token(&x.value).unwrap())
-                                            .collect::<Vec<_>>()
-                                            .as_slice(),
-                                    )
-                                    .into(),
-                                ),
-                                ..Default::default()
-                            }),
-                            None,
-                        )
-                        .await?;
+                let value = mint_value(&ctx, mint_config, price_abi.as_ref()).await?;
 
-                    let resp =
-                        ethers::abi::decode(&[ParamType::Uint(256)], resp.to_vec().as_slice())?;
-                    if let Some(ethers::abi::Token::Uint(a)) = resp.get(0) {
-                        U256::from(a.as_u128()) * U256::from(price_function.multiplier)
-                    } else {
-                        unreachable!();
-                    }
-                } else {
-                    U256::from(mint_config.value as u128)
-                };
-
-                for bundle in bundles.iter_mut() {
-                    for i in 0..total_txs {
-                        let mut tx = TypedTransaction::Eip1559(Eip1559TransactionRequest {
+                let gas_limit = match discovered_gas_limit {
+                    Some(gas_limit) => gas_limit,
+                    None => {
+                        let template = TypedTransaction::Eip1559(Eip1559TransactionRequest {
                             from: Some(our_addr),
                             to: Some(Address::from_str(&mint_config.contract_address)?.into()),
                             value: Some(value),
-                            data: Some(calldata.clone().to_vec().into()),
-                            nonce: Some(nonce + i),
-                            max_priority_fee_per_gas: Some(
-                                mint_config
-                                    .priority_fee
-                                    .map(|x| U256::from((x * 1e9) as u128))
-                                    .unwrap_or(gas_fee),
-                            ),
+                            data: Some(calldata.calldata.clone().into()),
+                            nonce: Some(nonce),
+                            max_priority_fee_per_gas: Some(fees.max_priority_fee_per_gas),
                             max_fee_per_gas: Some(gas_fee),
                             gas: None,
                             ..Default::default()
                         });
 
-                        let gas_limit = match mint_config.gas_limit {
-                            Some(limit) => U256::from(limit),
-                            None => panic!("gas estimation not available for flashbots"),
-                        };
+                        match discover_gas_limit(&ctx, mint_config, template, block_number).await {
+                            Ok(gas_limit) => {
+                                info!("discovered gas limit from bundle simulation: {}", gas_limit);
+                                discovered_gas_limit = Some(gas_limit);
+                                gas_limit
+                            }
+                            Err(e) => match mint_config.gas_limit {
+                                Some(limit) => {
+                                    warn!(
+                                        "error discovering gas limit ({}), using configured limit",
+                                        e
+                                    );
+                                    U256::from(limit)
+                                }
+                                None => {
+                                    error!("error discovering gas limit ({}), waiting for next block to try again...", e);
+                                    ctx.provider().watch_blocks().await?.next().await;
+                                    continue;
+                                }
+                            },
+                        }
+                    }
+                };
 
-                        tx.set_gas(gas_limit);
+                let mut access_lists = HashMap::new();
+                for bundle in bundles.iter_mut() {
+                    // every wallet mints in the same bundle
+                    let mut signed = Vec::new();
+                    for wallet in wallets.iter() {
+                        let nonce = nonces[&wallet.address()];
+                        let plan = plans[&wallet.address()];
+                        for i in 0..plan.transactions {
+                            // the same txs are sent for every block of the
+                            // bundle window, {block} is its first block
+                            let placeholders = Placeholders {
+                                address: wallet.address(),
+                                index: i,
+                                nonce: nonce + i,
+                                block: target_block.as_u64(),
+                                timestamp: match mint_config.start_time {
+                                    Some(start_time) if start_time > 0 => start_time + 1,
+                                    _ => shared::util::epoch_time().as_secs(),
+                                },
+                            };
+                            let calldata = calldata.for_tx(mint_config, &plan, &placeholders)?;
+                            let mut tx = TypedTransaction::Eip1559(Eip1559TransactionRequest {
+                                from: Some(wallet.address()),
+                                to: Some(Address::from_str(&mint_config.contract_address)?.into()),
+                                value: Some(value),
+                                data: Some(calldata.clone().into()),
+                                nonce: Some(nonce + i),
+                                max_priority_fee_per_gas: Some(fees.max_priority_fee_per_gas),
+                                max_fee_per_gas: Some(gas_fee),
+                                gas: None,
+                                ..Default::default()
+                            });
+
+                            tx.set_gas(gas_limit);
+                            if mint_config.access_list.unwrap_or(false) {
+                                attach_shared_access_list(
+                                    &ctx,
+                                    &mut access_lists,
+                                    &calldata,
+                                    &mut tx,
+                                )
+                                .await;
+                            }
 
-                        let signature = ctx.provider().signer().sign_transaction(&tx).await?;
-                        bundle.push_transaction(
-                            tx.rlp_signed(ctx.provider().signer().chain_id(), &signature),
-                        );
+                            let signature = wallet.sign_transaction(&tx).await?;
+                            let raw_tx = tx.rlp_signed(wallet.chain_id(), &signature);
+                            bundle.push_transaction(raw_tx.clone());
+                            signed.push((wallet.address(), raw_tx));
+                        }
+                    }
+                    if let Some((payment, value)) = coinbase_payment.as_ref() {
+                        bundle
+                            .push_coinbase_payment(
+                                ctx.provider().signer(),
+                                payment,
+                                *value,
+                                nonce + plans[&our_addr].transactions,
+                                gas_fee,
+                            )
+                            .await?;
                     }
 
                     bundle
                         .set_block(target_block)
                         .set_simulation_block(block_number)
-                        .set_simulation_timestamp(shared::util::epoch_time().as_secs());
+                        .set_simulation_timestamp(shared::util::epoch_time().as_secs())
+                        .set_replacement_uuid(replacement_uuid);
 
                     if let Some(start_time) = mint_config.start_time {
                         if start_time > 0 {
                             bundle
                                 .set_min_timestamp(start_time + 1)
-                                .set_max_timestamp(
-                                    start_time + Duration::hours(8).num_seconds() as u64,
-                                )
+                                .set_max_timestamp(start_time + validity)
                                 .set_simulation_timestamp(start_time + 1);
                         }
                     }
 
                     if ctx.config().account.simulate {
-                        let simulated_bundle = ctx.provider().inner().simulate_bundle(bundle).await;
+                        let simulated_bundle = ctx.simulate_bundle(bundle).await;
                         match simulated_bundle {
                             Ok(bundle) => {
                                 dbg!(&bundle);
@@ -437,242 +462,117 @@ pub async fn handle<M: 'static + Middleware + Clone, S: 'static + Signer + Clone
                         break;
                     }
 
-                    if ctx.send_bundle(bundle).await.is_ok() {
+                    if ctx.send_bundle(bundle, target_blocks).await.is_ok() {
+                        report_wallets(&wallets, &signed, "included");
+                        let receipts =
+                            results::wait_for_receipts(&ctx, &wallet_tx_hashes(&signed)).await?;
+                        report_receipts(mint_config, &wallets, &receipts).await;
                         break;
                     }
                 }
             },
-            MintMode::Normal => {
-                let nonce = match mint_config.initial_nonce {
-                    Some(nonce) if nonce > 0 => U256::from(nonce),
-                    None | Some(_) => ctx.provider().get_transaction_count(our_addr, None).await?,
-                };
+            MintMode::Normal | MintMode::Private => {
+                let mut nonces = wallet_nonces(&ctx, &wallets).await?;
+                // the initial nonce only applies to the account wallet
+                if let Some(nonce) = mint_config.initial_nonce.filter(|nonce| *nonce > 0) {
+                    nonces.insert(our_addr, U256::from(nonce));
+                }
 
-                dbg!(nonce);
+                debug!("nonces: {:?}", nonces);
 
                 let mut transactions = Vec::new();
 
                 if mint_config.bump_mempool.unwrap_or(false) {
                     if let Ok(mempool) = ctx.provider().txpool_content().await {
-                        let gas_fee = U256::from((mint_config.gas_fee * 1e9) as u128);
-                        if let Some(pending_txs) = mempool
-                            .pending
-                            .get(&our_addr)
-                            .map(|x| x.values().collect_vec())
-                        {
-                            for tx in pending_txs {
+                        let fees = fee_strategy.fees(ctx.provider(), 1).await?;
+                        for wallet in wallets.iter() {
+                            let address = wallet.address();
+                            let wallet_txs = mempool
+                                .pending
+                                .get(&address)
+                                .into_iter()
+                                .chain(mempool.queued.get(&address))
+                                .flat_map(|txs| txs.values());
+                            for tx in wallet_txs {
                                 let tx = TypedTransaction::Eip1559(Eip1559TransactionRequest {
-                                    from: Some(our_addr),
+                                    from: Some(address),
                                     to: Some(tx.to.unwrap().into()),
                                     value: Some(tx.value),
                                     data: Some(tx.input.clone()),
                                     nonce: Some(tx.nonce),
-                                    max_priority_fee_per_gas: Some(
-                                        mint_config
-                                            .priority_fee
-                                            .map(|x| U256::from((x * 1e9) as u128))
-                                            .unwrap_or(gas_fee),
-                                    ),
-                                    max_fee_per_gas: Some(gas_fee),
+                                    max_priority_fee_per_gas: Some(fees.max_priority_fee_per_gas),
+                                    max_fee_per_gas: Some(fees.max_fee_per_gas),
                                     gas: Some(tx.gas),
                                     ..Default::default()
                                 });
 
-                                let signature =
-                                    ctx.provider().signer().sign_transaction(&tx).await?;
-                                transactions.push(hex::encode(
-                                    &tx.rlp_signed(ctx.provider().signer().chain_id(), &signature)
-                                        .to_vec(),
-                                ));
-                            }
-                        }
-
-                        if let Some(queued_txs) = mempool
-                            .queued
-                            .get(&our_addr)
-                            .map(|x| x.values().collect_vec())
-                        {
-                            for tx in queued_txs {
-                                let tx = TypedTransaction::Eip1559(Eip1559TransactionRequest {
-                                    from: Some(our_addr),
-                                    to: Some(tx.to.unwrap().into()),
-                                    value: Some(tx.value),
-                                    data: Some(tx.input.clone()),
-                                    nonce: Some(tx.nonce),
-                                    max_priority_fee_per_gas: Some(
-                                        mint_config
-                                            .priority_fee
-                                            .map(|x| U256::from((x * 1e9) as u128))
-                                            .unwrap_or(gas_fee),
-                                    ),
-                                    max_fee_per_gas: Some(gas_fee),
-                                    gas: Some(tx.gas),
-                                    ..Default::default()
-                                });
-
-                                let signature =
-                                    ctx.provider().signer().sign_transaction(&tx).await?;
-                                transactions.push(hex::encode(
-                                    &tx.rlp_signed(ctx.provider().signer().chain_id(), &signature)
-                                        .to_vec(),
-                                ));
+                                let signature = wallet.sign_transaction(&tx).await?;
+                                transactions
+                                    .push((address, tx.rlp_signed(wallet.chain_id(), &signature)));
                             }
                         }
                     }
                 } else {
-                    let value = if let Some(price_function) = mint_config.price_function.as_ref() {
-                        let resp = ctx
-                            .provider()
-                            .call(
-                                &TypedTransaction::Eip1559(Eip1559TransactionRequest {
-                                    to: Some(NameOrAddress::Address(Address::from_str(
-                                        price_function
-                                            .address
-                                            .as_ref()
-                                            .unwrap_or(&mint_config.contract_address),
-                                    )?)),
-                                    data: Some(
-                                        contracts::encode_call(
-                                            &price_function.function,
-                                            price_function
-                                                .arguments
-                                                .iter()
-                                                .map(|x| x.r#type.to_token(&x.value).unwrap())
-                                                .collect::<Vec<_>>()
-                                                .as_slice(),
-                                        )
-                                        .into(),
-                                    ),
-                                    ..Default::default()
-                                }),
-                                None,
-                            )
-                            .await?;
-
-                        let resp =
-                            ethers::abi::decode(&[ParamType::Uint(256)], resp.to_vec().as_slice())?;
-                        if let Some(ethers::abi::Token::Uint(a)) = resp.get(0) {
-                            U256::from(a.as_u128()) * U256::from(price_function.multiplier)
-                        } else {
-                            unreachable!();
+                    let value = mint_value(&ctx, mint_config, price_abi.as_ref()).await?;
+
+                    let fees = fee_strategy.fees(ctx.provider(), 1).await?;
+                    // the backrun pays the same fees as the matching tx, up to
+                    // the max fee of the strategy
+                    let (max_fee, priority_fee) = match backrun_fees {
+                        Some((max_fee, priority_fee)) => {
+                            let max_fee = max_fee.min(fees.max_fee_per_gas);
+                            (max_fee, priority_fee.min(max_fee))
                         }
-                    } else {
-                        U256::from(mint_config.value as u128)
+                        None => (fees.max_fee_per_gas, fees.max_priority_fee_per_gas),
                     };
-
-                    let mut failed_simulation = false;
-                    let gas_fee = U256::from((mint_config.gas_fee * 1e9) as u128);
-                    for i in 0..mint_config.transaction_count.unwrap_or(1).max(1) {
-                        let mut tx = TypedTransaction::Eip1559(Eip1559TransactionRequest {
-                            from: Some(our_addr),
-                            to: Some(Address::from_str(&mint_config.contract_address)?.into()),
-                            value: Some(value),
-                            data: Some(calldata.clone().to_vec().into()),
-                            nonce: Some(nonce + i),
-                            max_priority_fee_per_gas: Some(
-                                mint_config
-                                    .priority_fee
-                                    .map(|x| U256::from((x * 1e9) as u128))
-                                    .unwrap_or(gas_fee),
-                            ),
-                            max_fee_per_gas: Some(gas_fee),
-                            gas: None,
-                            ..Default::default()
-                        });
-
-                        let gas_limit = match mint_config.gas_limit {
-                            Some(limit) => U256::from(limit),
-                            None => match ctx.provider().estimate_gas(&tx).await {
-                                Ok(gas_used) => {
-                                    U256::from((gas_used.as_u64() as f64 * 1.1).round() as u64)
-                                }
-                                Err(_) => {
-                                    error!("failed to estimate gas limit, waiting for next block to try again...");
-                                    failed_simulation = true;
-                                    break;
-                                }
-                            },
-                        };
-
-                        tx.set_gas(gas_limit);
-
-                        if ctx.config().account.simulate {
-                            if let Err(e) = ctx.provider().call(&tx, None).await {
-                                error!("transaction simulation failed, waiting for next block to try again. ({})", e);
-                                failed_simulation = true;
-                                break;
-                            }
+                    let template = Eip1559TransactionRequest::new()
+                        .to(Address::from_str(&mint_config.contract_address)?)
+                        .value(value)
+                        .max_fee_per_gas(max_fee)
+                        .max_priority_fee_per_gas(priority_fee);
+                    // a backrun only succeeds after the matching tx
+                    let simulate = ctx.config().account.simulate && backrun_fees.is_none();
+
+                    'sign: loop {
+                        transactions.clear();
+                        let plans = planner::plan(&ctx, mint_config, &wallets).await?;
+                        if plans.values().all(|plan| plan.transactions == 0) {
+                            info!("nothing left to mint, exiting.");
+                            return Ok(());
                         }
 
-                        let signature = ctx.provider().signer().sign_transaction(&tx).await?;
-                        transactions.push(hex::encode(
-                            &tx.rlp_signed(ctx.provider().signer().chain_id(), &signature)
-                                .to_vec(),
-                        ));
-                    }
+                        let block = ctx.provider().get_block_number().await? + 1;
+                        let mut access_lists = HashMap::new();
+                        for wallet in wallets.iter() {
+                            let plan = plans[&wallet.address()];
+                            if plan.transactions == 0 {
+                                continue;
+                            }
 
-                    if failed_simulation {
-                        'simulate: loop {
-                            let mut block_subscription = ctx.provider().watch_blocks().await?;
-                            'block: while let Some(_) = block_subscription.next().await {
-                                transactions.clear();
-                                for i in 0..mint_config.transaction_count.unwrap_or(1).max(1) {
-                                    let mut tx =
-                                        TypedTransaction::Eip1559(Eip1559TransactionRequest {
-                                            from: Some(our_addr),
-                                            to: Some(
-                                                Address::from_str(&mint_config.contract_address)?
-                                                    .into(),
-                                            ),
-                                            value: Some(value),
-                                            data: Some(calldata.clone().to_vec().into()),
-                                            nonce: Some(nonce + i),
-                                            max_priority_fee_per_gas: Some(
-                                                mint_config
-                                                    .priority_fee
-                                                    .map(|x| U256::from((x * 1e9) as u128))
-                                                    .unwrap_or(gas_fee),
-                                            ),
-                                            max_fee_per_gas: Some(gas_fee),
-                                            gas: None,
-                                            ..Default::default()
-                                        });
-
-                                    let gas_limit = match mint_config.gas_limit {
-                                        Some(limit) => U256::from(limit),
-                                        None => match ctx.provider().estimate_gas(&tx).await {
-                                            Ok(gas_used) => U256::from(
-                                                (gas_used.as_u64() as f64 * 1.1).round() as u64,
-                                            ),
-                                            Err(_) => {
-                                                error!("failed to estimate gas limit, waiting for next block to try again...");
-                                                break;
-                                            }
-                                        },
-                                    };
-
-                                    tx.set_gas(gas_limit);
-
-                                    if ctx.config().account.simulate {
-                                        if let Err(e) = ctx.provider().call(&tx, None).await {
-                                            error!("transaction simulation failed, waiting for next block to try again. ({})", e);
-                                            continue 'block;
-                                        }
-                                    }
-
-                                    let signature =
-                                        ctx.provider().signer().sign_transaction(&tx).await?;
-                                    transactions.push(hex::encode(
-                                        &tx.rlp_signed(
-                                            ctx.provider().signer().chain_id(),
-                                            &signature,
-                                        )
-                                        .to_vec(),
-                                    ));
+                            match sign_mint_txs(
+                                &ctx,
+                                mint_config,
+                                wallet,
+                                nonces[&wallet.address()],
+                                &template,
+                                &calldata,
+                                &plan,
+                                block.as_u64(),
+                                simulate,
+                                &mut access_lists,
+                            )
+                            .await?
+                            {
+                                Some(signed) => transactions.extend(
+                                    signed.into_iter().map(|raw_tx| (wallet.address(), raw_tx)),
+                                ),
+                                None => {
+                                    ctx.provider().watch_blocks().await?.next().await;
+                                    continue 'sign;
                                 }
-                                break 'simulate;
                             }
                         }
+                        break;
                     }
                 }
 
@@ -683,24 +583,63 @@ pub async fn handle<M: 'static + Middleware + Clone, S: 'static + Signer + Clone
                     return Ok(());
                 }
 
+                if mint_config.mode == MintMode::Private {
+                    let max_block_number = ctx.provider().get_block_number().await?
+                        + mint_config
+                            .private_tx_blocks
+                            .unwrap_or(DEFAULT_PRIVATE_TX_BLOCKS);
+
+                    let mut tx_hashes = Vec::new();
+                    for (_, raw_tx) in transactions.iter() {
+                        tx_hashes.push(
+                            ctx.send_private_transaction(raw_tx.clone(), max_block_number)
+                                .await?,
+                        );
+                    }
+
+                    let pending = ctx
+                        .wait_for_private_transactions(&tx_hashes, max_block_number)
+                        .await?;
+                    let included = transactions
+                        .iter()
+                        .filter(|(_, raw_tx)| !pending.contains(&H256::from(keccak256(raw_tx))))
+                        .cloned()
+                        .collect_vec();
+                    report_wallets(&wallets, &included, "included");
+                    let receipts =
+                        results::wait_for_receipts(&ctx, &wallet_tx_hashes(&included)).await?;
+                    report_receipts(mint_config, &wallets, &receipts).await;
+                    if pending.is_empty() {
+                        info!("all private transactions included, exiting.");
+                        return Ok(());
+                    }
+
+                    for tx_hash in pending {
+                        if let Err(e) = ctx.cancel_private_transaction(tx_hash).await {
+                            warn!("error cancelling private transaction: {}", e);
+                        }
+                    }
+                    info!("rebuilding private transactions...");
+                    continue;
+                }
+
                 let metamask_provider = Provider::<Http>::try_from(
                     "https://mainnet.infura.io/v3/9aa3d95b3bc440fa88ea12eaa4456161",
                 )?;
 
-                for tx in transactions {
+                let submitted_block = ctx.provider().get_block_number().await?;
+                for (address, raw_tx) in transactions.iter() {
+                    let tx = hex::encode(raw_tx.to_vec());
                     loop {
-                        let raw_tx = hex::decode(&tx).unwrap();
+                        let raw_tx = raw_tx.clone();
                         let mm = metamask_provider.clone();
                         let mm_tx = raw_tx.clone();
                         tokio::spawn(async move {
-                            let _ = mm.send_raw_transaction(mm_tx.into()).await;
+                            let _ = mm.send_raw_transaction(mm_tx).await;
                         });
                         let temp_ctx = ctx.clone();
                         tokio::spawn(async move {
-                            let _ = temp_ctx
-                                .provider()
-                                .send_raw_transaction(raw_tx.into())
-                                .await;
+                            let _ = temp_ctx.provider().send_raw_transaction(raw_tx).await;
                         });
                         let resp = ctx
                             .http()
@@ -721,7 +660,7 @@ pub async fn handle<M: 'static + Middleware + Clone, S: 'static + Signer + Clone
                             .and_then(|r| r.error_for_status());
                         match resp {
                             Ok(resp) => {
-                                info!("tx submitted: {}", resp.text().await?);
+                                info!("tx submitted for 0x{:x}: {}", address, resp.text().await?);
                                 break;
                             }
                             Err(e) => {
@@ -733,6 +672,21 @@ pub async fn handle<M: 'static + Middleware + Clone, S: 'static + Signer + Clone
                     }
                 }
 
+                report_wallets(&wallets, &transactions, "submitted");
+                let mut tracker =
+                    TransactionTracker::new(&ctx, &wallets, mint_config.tracker.as_ref());
+                for (_, raw_tx) in transactions.iter() {
+                    tracker.push(raw_tx, submitted_block)?;
+                }
+                let receipts = tracker
+                    .track()
+                    .await?
+                    .into_iter()
+                    .filter(|tx| !tx.cancelled)
+                    .filter_map(|tx| tx.receipt.map(|receipt| (tx.wallet, receipt)))
+                    .collect_vec();
+                info!("{} of {} tx(s) mined", receipts.len(), transactions.len());
+                report_receipts(mint_config, &wallets, &receipts).await;
                 info!("all transactions submitted successfully, exiting.");
 
                 return Ok(());
@@ -741,11 +695,404 @@ pub async fn handle<M: 'static + Middleware + Clone, S: 'static + Signer + Clone
     }
 }
 
+/// Fetches the next nonce of every wallet.
+async fn wallet_nonces<M: 'static + Middleware + Clone, S: 'static + Signer + Clone>(
+    ctx: &Context<M, S>,
+    wallets: &[LocalWallet],
+) -> Result<HashMap<Address, U256>, Error> {
+    let mut nonces = HashMap::with_capacity(wallets.len());
+    for wallet in wallets {
+        let nonce = ctx
+            .provider()
+            .get_transaction_count(wallet.address(), None)
+            .await?;
+        nonces.insert(wallet.address(), nonce);
+    }
+    Ok(nonces)
+}
+
+/// Builds and signs the mint txs of the plan of a wallet from the template,
+/// starting at `nonce`. Every tx gets its own calldata, with the placeholders
+/// of the arguments resolved for the tx and `block`. Access lists are shared
+/// by the txs with the same calldata, see [`attach_shared_access_list`].
+///
+/// Returns `None` if the gas limit could not be estimated, or if a tx failed
+/// in simulation.
+async fn sign_mint_txs<M: 'static + Middleware + Clone, S: 'static + Signer + Clone>(
+    ctx: &Context<M, S>,
+    mint_config: &Mint,
+    wallet: &LocalWallet,
+    nonce: U256,
+    template: &Eip1559TransactionRequest,
+    calldata: &planner::MintCalldata,
+    plan: &planner::MintPlan,
+    block: u64,
+    simulate: bool,
+    access_lists: &mut HashMap<Vec<u8>, Option<AccessList>>,
+) -> Result<Option<Vec<Bytes>>, Error> {
+    let mut signed = Vec::new();
+    for i in 0..plan.transactions {
+        let placeholders = Placeholders {
+            address: wallet.address(),
+            index: i,
+            nonce: nonce + i,
+            block,
+            timestamp: shared::util::epoch_time().as_secs(),
+        };
+        let calldata = calldata.for_tx(mint_config, plan, &placeholders)?;
+        let mut tx = TypedTransaction::Eip1559(
+            template
+                .clone()
+                .from(wallet.address())
+                .nonce(nonce + i)
+                .data(calldata.clone()),
+        );
+
+        let gas_limit = match mint_config.gas_limit {
+            Some(limit) => U256::from(limit),
+            None => match ctx.provider().estimate_gas(&tx).await {
+                Ok(gas_used) => U256::from((gas_used.as_u64() as f64 * 1.1).round() as u64),
+                Err(_) => {
+                    error!("failed to estimate gas limit, waiting for next block to try again...");
+                    return Ok(None);
+                }
+            },
+        };
+
+        tx.set_gas(gas_limit);
+        if mint_config.access_list.unwrap_or(false) {
+            attach_shared_access_list(ctx, access_lists, &calldata, &mut tx).await;
+        }
+
+        if simulate {
+            if let Err(e) = ctx.provider().call(&tx, None).await {
+                error!(
+                    "transaction simulation failed, waiting for next block to try again. ({})",
+                    e
+                );
+                return Ok(None);
+            }
+        }
+
+        let signature = wallet.sign_transaction(&tx).await?;
+        signed.push(tx.rlp_signed(wallet.chain_id(), &signature));
+    }
+
+    Ok(Some(signed))
+}
+
+/// Attaches the access list of `calldata` to a tx, creating it with the first
+/// tx that uses the calldata instead of once per tx.
+async fn attach_shared_access_list<M: 'static + Middleware + Clone, S: 'static + Signer + Clone>(
+    ctx: &Context<M, S>,
+    access_lists: &mut HashMap<Vec<u8>, Option<AccessList>>,
+    calldata: &[u8],
+    tx: &mut TypedTransaction,
+) {
+    if !access_lists.contains_key(calldata) {
+        let access_list = util::create_access_list(ctx.provider(), tx).await;
+        access_lists.insert(calldata.to_vec(), access_list);
+    }
+    if let Some(access_list) = access_lists[calldata].clone() {
+        util::set_access_list(tx, access_list);
+    }
+}
+
+/// Logs the hashes of the txs of every wallet.
+fn report_wallets(wallets: &[LocalWallet], transactions: &[(Address, Bytes)], outcome: &str) {
+    for wallet in wallets {
+        let tx_hashes = transactions
+            .iter()
+            .filter(|(address, _)| *address == wallet.address())
+            .map(|(_, raw_tx)| format!("0x{:x}", H256::from(keccak256(raw_tx))))
+            .collect_vec();
+        info!(
+            "0x{:x}: {} tx(s) {} {}",
+            wallet.address(),
+            tx_hashes.len(),
+            outcome,
+            tx_hashes.join(", ")
+        );
+    }
+}
+
+/// The hashes of the txs of every wallet.
+fn wallet_tx_hashes(transactions: &[(Address, Bytes)]) -> Vec<(Address, TxHash)> {
+    transactions
+        .iter()
+        .map(|(address, raw_tx)| (*address, H256::from(keccak256(raw_tx))))
+        .collect()
+}
+
+/// Logs what every wallet received in the receipts of its txs, and appends
+/// it to the results file of the config.
+async fn report_receipts(
+    mint_config: &Mint,
+    wallets: &[LocalWallet],
+    receipts: &[(Address, TransactionReceipt)],
+) {
+    let wallet_results = results::summarize(
+        &wallets.iter().map(|wallet| wallet.address()).collect_vec(),
+        receipts,
+    );
+    let path = mint_config
+        .results_file
+        .as_deref()
+        .filter(|path| !path.is_empty())
+        .unwrap_or(results::DEFAULT_RESULTS_FILE);
+    if let Err(e) = results::report(&mint_config.contract_address, path, &wallet_results).await {
+        warn!("error writing results: {}", e);
+    }
+}
+
+/// Matches pending transactions against the include address and method of
+/// the config.
+#[derive(Clone, Debug)]
+struct IncludeFilter {
+    address_type: IncludeAddressType,
+    address: Option<Address>,
+    method: Option<Vec<u8>>,
+}
+
+impl IncludeFilter {
+    /// Returns `None` if neither an include address nor method is set.
+    fn new(mint_config: &Mint) -> Result<Option<Self>, Error> {
+        let address = mint_config
+            .include_address
+            .as_ref()
+            .and_then(|a| Address::from_str(a).ok());
+        let method = match mint_config.include_method.as_ref() {
+            Some(sig) if sig.is_empty() => None,
+            Some(sig) if sig.starts_with("0x") => Some(shared::util::decode_hex(sig)?),
+            Some(sig) => Some(contracts::function_identifier(sig).to_vec()),
+            None => None,
+        };
+
+        if address.is_none() && method.is_none() {
+            return Ok(None);
+        }
+
+        Ok(Some(Self {
+            address_type: mint_config
+                .include_address_type
+                .clone()
+                .unwrap_or(IncludeAddressType::From),
+            address,
+            method,
+        }))
+    }
+
+    fn matches(&self, tx: &Transaction) -> bool {
+        if let Some(address) = self.address {
+            let matches_address = match self.address_type {
+                IncludeAddressType::From => tx.from == address,
+                IncludeAddressType::To => tx.to == Some(address),
+            };
+            if !matches_address {
+                return false;
+            }
+        }
+
+        match self.method.as_ref() {
+            Some(method) => tx.input.0.starts_with(method),
+            None => true,
+        }
+    }
+}
+
+/// Subscribes to pending transactions, and waits for the first one matching
+/// the include filter.
+async fn wait_for_include_tx<M: 'static + Middleware + Clone, S: 'static + Signer + Clone>(
+    ctx: &Context<M, S>,
+    include_filter: &IncludeFilter,
+) -> Result<Transaction, Error> {
+    let mut pending_txs = ctx.pubsub().subscribe_pending_txs().await?;
+    info!("waiting for matching tx in the mempool...");
+    while let Some(tx_hash) = pending_txs.next().await {
+        if let Ok(Some(tx)) = ctx.pubsub().get_transaction(tx_hash).await {
+            if include_filter.matches(&tx) {
+                info!("found matching include tx: 0x{:x}", tx.hash);
+                return Ok(tx);
+            }
+        }
+    }
+
+    Err("error subscribing to pending txs".into())
+}
+
+/// Subscribes to the logs of the event trigger, and waits for the first one
+/// with the expected values.
+async fn wait_for_event<M: 'static + Middleware + Clone, S: 'static + Signer + Clone>(
+    ctx: &Context<M, S>,
+    mint_config: &Mint,
+    trigger: &EventTrigger,
+) -> Result<(), Error> {
+    let matcher = checks::EventMatcher::new(trigger)?;
+    let filter = Filter::new()
+        .address(Address::from_str(
+            trigger
+                .address
+                .as_ref()
+                .unwrap_or(&mint_config.contract_address),
+        )?)
+        .topic0(matcher.topic());
+
+    let mut logs = ctx.pubsub().subscribe_logs(&filter).await?;
+    info!("waiting for {} event...", trigger.event);
+    while let Some(log) = logs.next().await {
+        if matcher.matches(&log) {
+            info!(
+                "found matching event in tx 0x{:x}, sending txs...",
+                log.transaction_hash.unwrap_or_default()
+            );
+            return Ok(());
+        }
+        warn!("event values did not match, waiting for next event...");
+    }
+
+    Err("error subscribing to events".into())
+}
+
+/// The value sent with every mint tx: the result of the price function
+/// times its multiplier if the config has one, or the configured value.
+async fn mint_value<M: 'static + Middleware + Clone, S: 'static + Signer + Clone>(
+    ctx: &Context<M, S>,
+    mint_config: &Mint,
+    price_abi: Option<&Abi>,
+) -> Result<U256, Error> {
+    let price_function = match mint_config.price_function.as_ref() {
+        Some(price_function) => price_function,
+        None => return Ok(U256::from(mint_config.value as u128)),
+    };
+
+    let (data, call) = match price_abi {
+        Some(price_abi) => {
+            let call = abi::resolve(
+                price_abi,
+                &price_function.function,
+                &price_function.arguments,
+            )?;
+            (call.encode()?, Some(call))
+        }
+        None => (
+            abi::encode_call(None, &price_function.function, &price_function.arguments)?,
+            None,
+        ),
+    };
+    let resp = ctx
+        .provider()
+        .call(
+            &TypedTransaction::Eip1559(Eip1559TransactionRequest {
+                to: Some(NameOrAddress::Address(Address::from_str(
+                    price_function
+                        .address
+                        .as_ref()
+                        .unwrap_or(&mint_config.contract_address),
+                )?)),
+                data: Some(data.into()),
+                ..Default::default()
+            }),
+            None,
+        )
+        .await?;
+
+    let resp = match call {
+        Some(call) => call.decode(resp.as_ref())?,
+        None => ethers::abi::decode(&[ParamType::Uint(256)], resp.as_ref())?,
+    };
+    match resp.get(0) {
+        Some(ethers::abi::Token::Uint(price)) => Ok(*price * U256::from(price_function.multiplier)),
+        _ => Err(format!("{} did not return a uint", price_function.function).into()),
+    }
+}
+
+/// Calls the functions of the state checks, with their ABIs in `abis` (in the
+/// same order), and combines their results according to the state checks
+/// mode of the config.
+async fn state_checks_pass<M: 'static + Middleware + Clone, S: 'static + Signer + Clone>(
+    ctx: &Context<M, S>,
+    mint_config: &Mint,
+    state_checks: &[StateChecks],
+    abis: &[Option<Abi>],
+) -> Result<bool, Error> {
+    let mut results = Vec::with_capacity(state_checks.len());
+    for (check, check_abi) in state_checks.iter().zip(abis) {
+        let resp = ctx
+            .provider()
+            .call(
+                &TypedTransaction::Eip1559(Eip1559TransactionRequest {
+                    to: Some(NameOrAddress::Address(Address::from_str(
+                        check
+                            .address
+                            .as_ref()
+                            .unwrap_or(&mint_config.contract_address),
+                    )?)),
+                    data: Some(
+                        abi::encode_call(check_abi.as_ref(), &check.function, &check.arguments)?
+                            .into(),
+                    ),
+                    ..Default::default()
+                }),
+                None,
+            )
+            .await?;
+
+        results.push(checks::check_state(
+            check,
+            check_abi.as_ref(),
+            resp.as_ref(),
+        )?);
+    }
+
+    Ok(checks::combine(
+        mint_config.state_checks_mode.as_ref(),
+        &results,
+    ))
+}
+
+/// Discovers the gas limit of Flashbots mint transactions by simulating a
+/// bundle with a single mint transaction, at the start time of the mint if it
+/// is set, and adding the safety margin of the config to the gas it used.
+async fn discover_gas_limit<M: 'static + Middleware + Clone, S: 'static + Signer + Clone>(
+    ctx: &Context<M, S>,
+    mint_config: &Mint,
+    mut tx: TypedTransaction,
+    block_number: U64,
+) -> Result<U256, Error> {
+    tx.set_gas(TEMPLATE_GAS_LIMIT);
+    let signature = ctx.provider().signer().sign_transaction(&tx).await?;
+
+    let mut bundle = BundleRequest::new();
+    bundle
+        .push_transaction(tx.rlp_signed(ctx.provider().signer().chain_id(), &signature))
+        .set_block(block_number + 1)
+        .set_simulation_block(block_number)
+        .set_simulation_timestamp(match mint_config.start_time {
+            Some(start_time) if start_time > 0 => start_time + 1,
+            _ => shared::util::epoch_time().as_secs(),
+        });
+
+    let simulated_bundle = ctx.simulate_bundle(&bundle).await?;
+    let simulated_tx = simulated_bundle
+        .transactions
+        .get(0)
+        .ok_or("simulated bundle has no transactions")?;
+    if let Some(error) = simulated_tx.error.as_ref() {
+        return Err(format!("mint transaction failed in simulation: {}", error).into());
+    }
+
+    let margin = mint_config.gas_margin.unwrap_or(DEFAULT_GAS_MARGIN).max(0.);
+    Ok(U256::from(
+        (simulated_tx.gas_used.as_u64() as f64 * (1. + margin)).ceil() as u64,
+    ))
+}
+
 async fn generate_calldata<M: 'static + Middleware + Clone, S: 'static + Signer + Clone>(
     ctx: Context<M, S>,
     mint_config: &Mint,
-) -> Vec<u8> {
-    match mint_config.script_identifier.as_ref() {
+    mint_abi: Option<&Abi>,
+) -> Result<Vec<u8>, Error> {
+    Ok(match mint_config.script_identifier.as_ref() {
         Some(script_identifier) if !script_identifier.is_empty() => {
             let script_identifier = script_identifier.clone();
             let (tx, rx) = oneshot::channel::<MintInfo>();
@@ -861,28 +1208,27 @@ async fn generate_calldata<M: 'static + Middleware + Clone, S: 'static + Signer
             let mint_info = rx.await.unwrap();
 
             let calldata = match (mint_info.function, mint_info.arguments, mint_info.raw) {
-                (Some(function), Some(arguments), None) => shared::contracts::encode_call(
-                    &function,
-                    arguments
-                        .iter()
-                        .map(|x| x.r#type.to_token(&x.value).unwrap())
-                        .collect::<Vec<_>>()
-                        .as_slice(),
-                ),
+                (Some(function), Some(arguments), None) => {
+                    abi::encode_call(mint_abi, &function, &arguments)?
+                }
                 (None, None, Some(raw)) => shared::util::decode_hex(&raw).unwrap(),
                 _ => unreachable!(),
             };
             dbg!(hex::encode(&calldata));
             calldata
         }
-        _ => shared::contracts::encode_call(
+        // mint txs resolve the placeholders of their own arguments, this is
+        // the calldata of a tx of the account in the next block
+        _ => abi::encode_call(
+            mint_abi,
             &mint_config.function,
-            mint_config
-                .arguments
-                .iter()
-                .map(|x| x.r#type.to_token(&x.value).unwrap())
-                .collect::<Vec<_>>()
-                .as_slice(),
-        ),
-    }
+            &Placeholders {
+                address: ctx.provider().signer().address(),
+                block: ctx.provider().get_block_number().await?.as_u64() + 1,
+                timestamp: shared::util::epoch_time().as_secs(),
+                ..Default::default()
+            }
+            .arguments(&mint_config.arguments),
+        )?,
+    })
 }
@@ -1,2 +1,255 @@
 pub mod gql;
 pub mod rest;
+
+use crate::{
+    fees,
+    fees::{FeeStrategy, Fees},
+    flashbots::{BundleRequest, COINBASE_PAYMENT_GAS},
+    model::OldOrder,
+    opensea, util, Context, Error, DEFAULT_PRIVATE_TX_BLOCKS,
+};
+use ethers::prelude::{transaction::eip2718::TypedTransaction, *};
+use log::*;
+use shared::config::{OSSubmission, SmartGas};
+use uuid::Uuid;
+
+/// An order found by one of the OpenSea APIs.
+#[derive(Clone, Copy)]
+enum LimitOrder<'a> {
+    GraphQL(&'a OldOrder),
+    Rest(&'a opensea::Order),
+}
+
+impl LimitOrder<'_> {
+    /// The tx fulfilling the order with the given fees.
+    async fn to_tx<M: 'static + Middleware, S: 'static + Signer + Clone>(
+        self,
+        ctx: &Context<M, S>,
+        our_addr: Address,
+        fees: &Fees,
+        nonce: U256,
+    ) -> Result<TypedTransaction, Error> {
+        match self {
+            LimitOrder::GraphQL(order) => {
+                util::order_to_tx(
+                    ctx.config(),
+                    ctx.provider(),
+                    our_addr,
+                    order,
+                    fees.max_fee_per_gas,
+                    fees.max_priority_fee_per_gas,
+                    nonce,
+                )
+                .await
+            }
+            LimitOrder::Rest(order) => {
+                util::new_order_to_tx(
+                    ctx.config(),
+                    ctx.provider(),
+                    our_addr,
+                    order,
+                    fees.max_fee_per_gas,
+                    fees.max_priority_fee_per_gas,
+                    nonce,
+                )
+                .await
+            }
+        }
+    }
+}
+
+/// Buys an order with the submission of the OpenSea config, retrying up to
+/// the maximum retry attempts, and spending at most `maximum_price` on the
+/// order and its fees.
+async fn send_tx<M: 'static + Middleware, S: 'static + Signer + Clone>(
+    ctx: &Context<M, S>,
+    our_addr: Address,
+    maximum_price: U256,
+    base_price: U256,
+    order: LimitOrder<'_>,
+) -> Result<(), Error> {
+    let opensea_config = ctx
+        .config()
+        .opensea
+        .as_ref()
+        .expect("expected OpenSea config");
+    let fee_strategy = FeeStrategy::for_task(
+        ctx.config(),
+        opensea_config.fee_strategy.as_ref(),
+        opensea_config.gas_fee,
+        opensea_config.priority_fee,
+    )?;
+    let target_blocks = opensea_config
+        .bundle_window
+        .as_ref()
+        .and_then(|w| w.blocks)
+        .unwrap_or(1)
+        .max(1);
+    // every retry replaces the previous bundle instead of stacking a new one
+    let replacement_uuid = Uuid::new_v4();
+    let submission = opensea_config
+        .submission
+        .clone()
+        .unwrap_or(OSSubmission::Bundle);
+    // only bundles can pay the builder directly, the rest of the smart gas
+    // budget goes to the priority fee
+    let coinbase_payment = match opensea_config.coinbase_payment.as_ref() {
+        Some(payment_config) if submission == OSSubmission::Bundle => {
+            util::coinbase_payment(payment_config)?.map(|payment| {
+                let budget = if opensea_config.smart_gas == SmartGas::Disabled {
+                    U256::zero()
+                } else {
+                    maximum_price - base_price
+                };
+                (
+                    payment,
+                    util::coinbase_payment_value(payment_config, budget),
+                )
+            })
+        }
+        _ => None,
+    };
+    let gas_limit = U256::from(opensea_config.gas_limit);
+    let mut included = false;
+    for _ in 0..opensea_config.maximum_retry_attempts {
+        let nonce = ctx.provider().get_transaction_count(our_addr, None).await?;
+
+        let fees = fee_strategy.fees(ctx.provider(), target_blocks).await?;
+        let mut gas_budget = maximum_price - base_price;
+        let mut payment = None;
+        if let Some((coinbase_payment, value)) = coinbase_payment.as_ref() {
+            let base_fee = fees::base_fee(ctx.provider(), target_blocks).await?;
+            // the payment tx pays the base fee out of the same budget, and
+            // the payment shrinks so that the tx can still pay the base fee
+            let payment_gas = U256::from(COINBASE_PAYMENT_GAS) * fees.max_fee_per_gas.max(base_fee);
+            let value = (*value).min(gas_budget.saturating_sub(payment_gas + gas_limit * base_fee));
+            if value.is_zero() {
+                warn!("the smart gas budget cannot cover the coinbase payment, skipping it");
+            } else {
+                gas_budget = gas_budget.saturating_sub(payment_gas + value);
+                payment = Some((coinbase_payment, value));
+            }
+        }
+        let fees = fees.with_smart_gas(&opensea_config.smart_gas, gas_budget / gas_limit);
+        let gas_fee = fees.max_fee_per_gas;
+
+        let tx = order.to_tx(ctx, our_addr, &fees, nonce).await?;
+        let signature = ctx.provider().signer().sign_transaction(&tx).await?;
+
+        if submission == OSSubmission::Private {
+            if ctx.config().account.dry_run {
+                info!("Dry run, exiting early. Did not send transaction.");
+                break;
+            }
+
+            let max_block_number = ctx.provider().get_block_number().await?
+                + opensea_config
+                    .private_tx_blocks
+                    .unwrap_or(DEFAULT_PRIVATE_TX_BLOCKS);
+            let tx_hash = ctx
+                .send_private_transaction(
+                    tx.rlp_signed(ctx.provider().signer().chain_id(), &signature),
+                    max_block_number,
+                )
+                .await?;
+            if ctx
+                .wait_for_private_transactions(&[tx_hash], max_block_number)
+                .await?
+                .is_empty()
+            {
+                break;
+            }
+
+            if let Err(e) = ctx.cancel_private_transaction(tx_hash).await {
+                warn!("error cancelling private transaction: {}", e);
+            }
+            continue;
+        }
+
+        if submission == OSSubmission::MevShare {
+            let block_number = ctx.provider().get_block_number().await?;
+            let bundle = util::mev_share_bundle(
+                ctx.config(),
+                tx.rlp_signed(ctx.provider().signer().chain_id(), &signature),
+                block_number + 1,
+                target_blocks,
+            )?;
+
+            if ctx.config().account.simulate {
+                match ctx.simulate_mev_bundle(&bundle).await {
+                    Ok(simulated_bundle) => {
+                        debug!(
+                            "MEV-Share bundle for block {} simulated at {} gwei",
+                            block_number + 1,
+                            simulated_bundle.mev_gas_price.as_u64() as f64 / 1e9
+                        );
+                    }
+                    Err(e) => {
+                        error!("error simulating bundle: {}", e);
+                        break;
+                    }
+                }
+            }
+
+            if ctx.config().account.dry_run {
+                info!("Dry run, exiting early. Did not send bundle.");
+                break;
+            }
+
+            if ctx.send_mev_bundle(&bundle).await.is_ok() {
+                break;
+            }
+            continue;
+        }
+
+        let mut bundle = BundleRequest::new();
+        bundle.push_transaction(tx.rlp_signed(ctx.provider().signer().chain_id(), &signature));
+        if let Some((payment, value)) = payment {
+            bundle
+                .push_coinbase_payment(ctx.provider().signer(), payment, value, nonce + 1, gas_fee)
+                .await?;
+        }
+
+        let block_number = ctx.provider().get_block_number().await?;
+        let target_block = block_number + 1;
+
+        bundle
+            .set_block(target_block)
+            .set_simulation_block(block_number)
+            .set_simulation_timestamp(shared::util::epoch_time().as_secs())
+            .set_replacement_uuid(replacement_uuid);
+
+        if ctx.config().account.simulate {
+            match ctx.simulate_bundle(&bundle).await {
+                Ok(simulated_bundle) => {
+                    dbg!(
+                        target_block,
+                        simulated_bundle.effective_gas_price().as_u64() as f64 / 1e9
+                    );
+                }
+                Err(e) => {
+                    error!("error simulating bundle: {}", e);
+                    break;
+                }
+            }
+        }
+
+        if ctx.config().account.dry_run {
+            info!("Dry run, exiting early. Did not send bundle.");
+            break;
+        }
+
+        if ctx.send_bundle(&bundle, target_blocks).await.is_ok() {
+            included = true;
+            break;
+        }
+    }
+
+    if submission == OSSubmission::Bundle && !included && !ctx.config().account.dry_run {
+        if let Err(e) = ctx.cancel_bundle(replacement_uuid).await {
+            warn!("error cancelling bundle: {}", e);
+        }
+    }
+
+    Ok(())
+}
@@ -1,8 +1,6 @@
 use crate::{
-    flashbots::BundleRequest,
     opensea,
     opensea::{AssetEvent, AssetEvents, Orders},
-    util,
     util::NULL_ADDR,
     Context,
 };
@@ -13,7 +11,7 @@ use itertools::Itertools;
 use log::*;
 use rand::{prelude::*, thread_rng};
 use reqwest::StatusCode;
-use shared::config::{OSLimitMode, SmartGas};
+use shared::config::OSLimitMode;
 use tokio::time::Duration;
 
 pub async fn handle<M: 'static + Middleware, S: 'static + Signer + Clone>(
@@ -145,7 +143,14 @@ async fn collection_loop<M: 'static + Middleware, S: 'static + Signer + Clone>(
                 }
 
                 info!("found matching order");
-                send_tx(ctx, our_addr, maximum_price, base_price, &order).await?;
+                super::send_tx(
+                    ctx,
+                    our_addr,
+                    maximum_price,
+                    base_price,
+                    super::LimitOrder::Rest(&order),
+                )
+                .await?;
             }
         }
 
@@ -224,94 +229,19 @@ async fn token_loop<M: 'static + Middleware, S: 'static + Signer + Clone>(
                 base_price.as_u64() as f64 / 1e18,
                 order.quantity
             );
-            send_tx(ctx, our_addr, maximum_price, base_price, &order).await?;
+            super::send_tx(
+                ctx,
+                our_addr,
+                maximum_price,
+                base_price,
+                super::LimitOrder::Rest(&order),
+            )
+            .await?;
         }
         ctx.delay("processed listings").await;
     }
 }
 
-async fn send_tx<M: 'static + Middleware, S: 'static + Signer + Clone>(
-    ctx: &Context<M, S>,
-    our_addr: Address,
-    maximum_price: U256,
-    base_price: U256,
-    order: &opensea::Order,
-) -> Result<(), shared::Error> {
-    let opensea_config = ctx
-        .config()
-        .opensea
-        .as_ref()
-        .expect("expected OpenSea config");
-    let base_gas_fee = U256::from((opensea_config.gas_fee * 1e9) as u128);
-    for _ in 0..opensea_config.maximum_retry_attempts {
-        let nonce = ctx.provider().get_transaction_count(our_addr, None).await?;
-
-        let gas_fee = match opensea_config.smart_gas {
-            SmartGas::Enabled => {
-                base_gas_fee.max((maximum_price - base_price) / opensea_config.gas_limit)
-            }
-            SmartGas::Disabled => base_gas_fee,
-            SmartGas::Exclusive => (maximum_price - base_price) / opensea_config.gas_limit,
-        };
-
-        let tx = util::new_order_to_tx(
-            ctx.config(),
-            ctx.provider(),
-            our_addr,
-            order,
-            gas_fee,
-            if opensea_config.smart_gas == SmartGas::Exclusive {
-                gas_fee
-            } else {
-                opensea_config
-                    .priority_fee
-                    .map(|x| U256::from((x * 1e9) as u128))
-                    .unwrap_or(gas_fee)
-            },
-            nonce,
-        )
-        .await?;
-        let signature = ctx.provider().signer().sign_transaction(&tx).await?;
-
-        let mut bundle = BundleRequest::new();
-        bundle.push_transaction(tx.rlp_signed(ctx.provider().signer().chain_id(), &signature));
-
-        let block_number = ctx.provider().get_block_number().await?;
-        let target_block = block_number + 1;
-
-        bundle
-            .set_block(target_block)
-            .set_simulation_block(block_number)
-            .set_simulation_timestamp(shared::util::epoch_time().as_secs());
-
-        if ctx.config().account.simulate {
-            match ctx.provider().inner().simulate_bundle(&bundle).await {
-                Ok(simulated_bundle) => {
-                    dbg!(
-                        target_block,
-                        simulated_bundle.effective_gas_price().as_u64() as f64 / 1e9
-                    );
-                }
-                Err(e) => {
-                    error!("error simulating bundle: {}", e);
-                    break;
-                }
-            }
-        }
-
-        if ctx.config().account.dry_run {
-            info!("Dry run, exiting early. Did not send bundle.");
-            break;
-        }
-
-        if ctx.send_bundle(&bundle).await.is_ok() {
-            break;
-        }
-    }
-
-    Ok(())
-}
-
 #[async_recursion]
 async fn fetch_listings<M: 'static + Middleware, S: 'static + Signer + Clone>(
     ctx: &Context<M, S>,
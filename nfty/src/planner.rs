@@ -0,0 +1,270 @@
+use crate::{Context, Error};
+use ethers::{
+    abi::{Abi, ParamType, Token},
+    prelude::{transaction::eip2718::TypedTransaction, *},
+};
+use log::*;
+use shared::{abi, config::Mint, contracts, token::Placeholders};
+use std::{collections::HashMap, str::FromStr};
+
+/// How many txs a wallet sends in an attempt, and the quantity every tx
+/// mints. A wallet with no txs skips the attempt.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MintPlan {
+    pub transactions: u64,
+    pub quantity: u64,
+}
+
+/// Plans the txs of every wallet, so that they do not mint more than the
+/// supply left and the limit of the wallet.
+///
+/// Every wallet sends the configured txs and quantity if the config has no
+/// supply getters. Getters that fail do not limit the plan.
+pub async fn plan<M: 'static + Middleware + Clone, S: 'static + Signer + Clone>(
+    ctx: &Context<M, S>,
+    mint_config: &Mint,
+    wallets: &[LocalWallet],
+) -> Result<HashMap<Address, MintPlan>, Error> {
+    let configured = MintPlan {
+        transactions: mint_config.transaction_count.unwrap_or(1).max(1),
+        quantity: configured_quantity(mint_config),
+    };
+    let supply = match mint_config.supply.as_ref() {
+        Some(supply) => supply,
+        None => {
+            return Ok(wallets
+                .iter()
+                .map(|wallet| (wallet.address(), configured))
+                .collect())
+        }
+    };
+    let address = Address::from_str(
+        supply
+            .address
+            .as_ref()
+            .unwrap_or(&mint_config.contract_address),
+    )?;
+
+    let mut supply_left = match (supply.total_supply.as_ref(), supply.max_supply.as_ref()) {
+        (Some(total_supply), Some(max_supply)) => {
+            let total_supply = limit(ctx, address, total_supply, vec![]).await;
+            let max_supply = limit(ctx, address, max_supply, vec![]).await;
+            total_supply
+                .zip(max_supply)
+                .map(|(total_supply, max_supply)| max_supply.saturating_sub(total_supply))
+        }
+        _ => None,
+    };
+    let wallet_limit = match supply.wallet_limit.as_ref() {
+        Some(wallet_limit) => limit(ctx, address, wallet_limit, vec![]).await,
+        None => None,
+    };
+
+    let mut plans = HashMap::with_capacity(wallets.len());
+    for wallet in wallets {
+        let mut allowed = supply_left.unwrap_or(u64::MAX);
+        let mut reason = "supply left";
+        if let Some(wallet_limit) = wallet_limit {
+            let minted = match supply.wallet_minted.as_ref() {
+                Some(wallet_minted) => {
+                    limit(
+                        ctx,
+                        address,
+                        wallet_minted,
+                        vec![Token::Address(wallet.address())],
+                    )
+                    .await
+                }
+                None => Some(0),
+            };
+            if let Some(minted) = minted {
+                if wallet_limit.saturating_sub(minted) < allowed {
+                    allowed = wallet_limit.saturating_sub(minted);
+                    reason = "left for the wallet";
+                }
+            }
+        }
+
+        let plan = fit(configured, allowed, supply.quantity_argument.is_some());
+        if plan.transactions == 0 {
+            info!(
+                "0x{:x}: skipping attempt, {} {}",
+                wallet.address(),
+                allowed,
+                reason
+            );
+        } else if plan != configured {
+            info!(
+                "0x{:x}: {} {}, sending {} tx(s) of {}",
+                wallet.address(),
+                allowed,
+                reason,
+                plan.transactions,
+                plan.quantity
+            );
+        }
+
+        if let Some(supply_left) = supply_left.as_mut() {
+            *supply_left =
+                supply_left.saturating_sub(plan.transactions.saturating_mul(plan.quantity));
+        }
+        plans.insert(wallet.address(), plan);
+    }
+    Ok(plans)
+}
+
+/// The calldata of the mint txs of a task, with the ABI of the config loaded
+/// once for the task instead of for every tx.
+pub struct MintCalldata {
+    /// The calldata of a tx of the account, with the configured quantity.
+    pub calldata: Vec<u8>,
+    abi: Option<Abi>,
+}
+
+impl MintCalldata {
+    pub fn new(calldata: Vec<u8>, abi: Option<Abi>) -> Self {
+        Self { calldata, abi }
+    }
+
+    /// The calldata of a mint tx, with the quantity of the plan and the
+    /// placeholders of the arguments resolved, or the configured calldata if
+    /// neither changes it. Calldata from a script is never changed.
+    pub fn for_tx(
+        &self,
+        mint_config: &Mint,
+        plan: &MintPlan,
+        placeholders: &Placeholders,
+    ) -> Result<Vec<u8>, Error> {
+        let quantity_argument = mint_config
+            .supply
+            .as_ref()
+            .and_then(|supply| supply.quantity_argument)
+            .filter(|index| *index < mint_config.arguments.len())
+            .filter(|_| plan.quantity != configured_quantity(mint_config));
+        let templated = Placeholders::any(&mint_config.arguments);
+        let from_script =
+            matches!(mint_config.script_identifier.as_ref(), Some(s) if !s.is_empty());
+        if from_script || (quantity_argument.is_none() && !templated) {
+            return Ok(self.calldata.clone());
+        }
+
+        let mut arguments = placeholders.arguments(&mint_config.arguments);
+        if let Some(index) = quantity_argument {
+            arguments[index].value = toml::Value::Integer(plan.quantity as i64);
+        }
+        abi::encode_call(self.abi.as_ref(), &mint_config.function, &arguments)
+    }
+}
+
+/// The quantity argument of the mint function, or 1 if it has none.
+fn configured_quantity(mint_config: &Mint) -> u64 {
+    mint_config
+        .supply
+        .as_ref()
+        .and_then(|supply| supply.quantity_argument)
+        .and_then(|index| mint_config.arguments.get(index))
+        .and_then(|argument| argument.value.as_integer())
+        .map(|quantity| quantity.max(1) as u64)
+        .unwrap_or(1)
+}
+
+/// Shrinks the configured txs to mint at most `allowed`: first by sending
+/// fewer txs, then by lowering the quantity of a single tx if the quantity
+/// can be changed.
+fn fit(configured: MintPlan, allowed: u64, adjustable_quantity: bool) -> MintPlan {
+    let MintPlan {
+        transactions,
+        quantity,
+    } = configured;
+    if transactions.saturating_mul(quantity) <= allowed {
+        configured
+    } else if allowed >= quantity {
+        MintPlan {
+            transactions: allowed / quantity,
+            quantity,
+        }
+    } else if adjustable_quantity && allowed > 0 {
+        MintPlan {
+            transactions: 1,
+            quantity: allowed,
+        }
+    } else {
+        MintPlan {
+            transactions: 0,
+            quantity,
+        }
+    }
+}
+
+/// Calls a supply getter, or returns `None` if the call fails, in which case
+/// it does not limit the plan.
+async fn limit<M: 'static + Middleware + Clone, S: 'static + Signer + Clone>(
+    ctx: &Context<M, S>,
+    address: Address,
+    function: &str,
+    arguments: Vec<Token>,
+) -> Option<u64> {
+    match call_uint(ctx, address, function, arguments).await {
+        Ok(value) => Some(value),
+        Err(e) => {
+            warn!("error calling {}, ignoring its limit: {}", function, e);
+            None
+        }
+    }
+}
+
+async fn call_uint<M: 'static + Middleware + Clone, S: 'static + Signer + Clone>(
+    ctx: &Context<M, S>,
+    address: Address,
+    function: &str,
+    arguments: Vec<Token>,
+) -> Result<u64, Error> {
+    let resp = ctx
+        .provider()
+        .call(
+            &TypedTransaction::Eip1559(Eip1559TransactionRequest {
+                to: Some(NameOrAddress::Address(address)),
+                data: Some(contracts::encode_call(function, arguments.as_slice()).into()),
+                ..Default::default()
+            }),
+            None,
+        )
+        .await?;
+
+    match ethers::abi::decode(&[ParamType::Uint(256)], resp.as_ref())?.get(0) {
+        Some(Token::Uint(value)) => Ok((*value).min(U256::from(u64::MAX)).as_u64()),
+        _ => Err(format!("{} did not return a uint", function).into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn plan(transactions: u64, quantity: u64) -> MintPlan {
+        MintPlan {
+            transactions,
+            quantity,
+        }
+    }
+
+    #[test]
+    fn planner_fit() {
+        // the configured txs fit
+        assert_eq!(fit(plan(3, 5), 15, false), plan(3, 5));
+        assert_eq!(fit(plan(3, 5), u64::MAX, false), plan(3, 5));
+
+        // fewer txs are sent first, without minting more than allowed
+        assert_eq!(fit(plan(3, 5), 7, false), plan(1, 5));
+        assert_eq!(fit(plan(3, 5), 7, true), plan(1, 5));
+        assert_eq!(fit(plan(3, 5), 14, true), plan(2, 5));
+
+        // then the quantity of a single tx is lowered, if it can be
+        assert_eq!(fit(plan(3, 5), 4, true), plan(1, 4));
+        assert_eq!(fit(plan(3, 5), 4, false), plan(0, 5));
+
+        // nothing is sent if nothing is allowed
+        assert_eq!(fit(plan(3, 5), 0, true), plan(0, 5));
+        assert_eq!(fit(plan(1, 1), 0, false), plan(0, 1));
+    }
+}
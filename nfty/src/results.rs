@@ -0,0 +1,307 @@
+use crate::{Context, Error};
+use ethers::{
+    abi::{ParamType, Token},
+    prelude::*,
+};
+use ethers_core::utils::keccak256;
+use log::*;
+use serde::Serialize;
+use tokio::io::AsyncWriteExt;
+
+/// The file mint results are appended to, by default.
+pub const DEFAULT_RESULTS_FILE: &str = "results.jsonl";
+
+const TRANSFER_EVENT: &str = "Transfer(address,address,uint256)";
+const TRANSFER_SINGLE_EVENT: &str = "TransferSingle(address,address,address,uint256,uint256)";
+const TRANSFER_BATCH_EVENT: &str = "TransferBatch(address,address,address,uint256[],uint256[])";
+
+/// A token received by a wallet.
+#[derive(Debug, Clone, Serialize)]
+pub struct MintedToken {
+    pub contract: Address,
+    pub token_id: String,
+    pub amount: String,
+}
+
+/// What a wallet got out of a mint, and what it paid for it.
+#[derive(Debug, Clone, Serialize)]
+pub struct WalletResult {
+    pub wallet: Address,
+    pub transactions: Vec<TxHash>,
+    pub tokens: Vec<MintedToken>,
+    pub gas_used: u64,
+    pub effective_gas_price: f64, // in gwei
+}
+
+#[derive(Serialize)]
+struct MintResults<'a> {
+    timestamp: u64,
+    contract: &'a str,
+    wallets: &'a [WalletResult],
+}
+
+/// Waits for the receipts of the transactions of every wallet.
+///
+/// The transactions must be mined already (or about to be), since the
+/// receipts are polled once per block until they are all found.
+pub async fn wait_for_receipts<M: 'static + Middleware + Clone, S: 'static + Signer + Clone>(
+    ctx: &Context<M, S>,
+    tx_hashes: &[(Address, TxHash)],
+) -> Result<Vec<(Address, TransactionReceipt)>, Error> {
+    let mut receipts = Vec::with_capacity(tx_hashes.len());
+    let mut pending = tx_hashes.to_vec();
+    let mut blocks = None;
+    loop {
+        let mut still_pending = Vec::new();
+        for (wallet, tx_hash) in pending {
+            match ctx.provider().get_transaction_receipt(tx_hash).await? {
+                Some(receipt) => receipts.push((wallet, receipt)),
+                None => still_pending.push((wallet, tx_hash)),
+            }
+        }
+        if still_pending.is_empty() {
+            return Ok(receipts);
+        }
+        pending = still_pending;
+
+        if blocks.is_none() {
+            blocks = Some(ctx.pubsub().subscribe_blocks().await?);
+        }
+        if let Some(blocks) = blocks.as_mut() {
+            if blocks.next().await.is_none() {
+                return Err("error subscribing to blocks".into());
+            }
+        }
+    }
+}
+
+/// Sums up the tokens received and the gas paid by every wallet in the
+/// receipts of its transactions.
+pub fn summarize(
+    wallets: &[Address],
+    receipts: &[(Address, TransactionReceipt)],
+) -> Vec<WalletResult> {
+    wallets
+        .iter()
+        .map(|wallet| {
+            let receipts = receipts
+                .iter()
+                .filter(|(address, _)| address == wallet)
+                .map(|(_, receipt)| receipt)
+                .collect::<Vec<_>>();
+            let gas_used = receipts
+                .iter()
+                .map(|receipt| receipt.gas_used.unwrap_or_default())
+                .fold(U256::zero(), |sum, gas_used| sum + gas_used);
+            let fees = receipts
+                .iter()
+                .map(|receipt| {
+                    receipt.gas_used.unwrap_or_default()
+                        * receipt.effective_gas_price.unwrap_or_default()
+                })
+                .fold(U256::zero(), |sum, fees| sum + fees);
+
+            WalletResult {
+                wallet: *wallet,
+                transactions: receipts
+                    .iter()
+                    .map(|receipt| receipt.transaction_hash)
+                    .collect(),
+                tokens: receipts
+                    .iter()
+                    .flat_map(|receipt| minted_tokens(receipt, *wallet))
+                    .collect(),
+                gas_used: gas_used.as_u64(),
+                effective_gas_price: if gas_used.is_zero() {
+                    0.
+                } else {
+                    (fees / gas_used).as_u128() as f64 / 1e9
+                },
+            }
+        })
+        .collect()
+}
+
+/// Logs the results of every wallet, and appends them to the results file.
+pub async fn report(contract: &str, path: &str, results: &[WalletResult]) -> Result<(), Error> {
+    for result in results {
+        info!(
+            "0x{:x}: received {} token(s) [{}] in {} tx(s), {} gas at {} gwei",
+            result.wallet,
+            result.tokens.len(),
+            result
+                .tokens
+                .iter()
+                .map(|token| token.token_id.as_str())
+                .collect::<Vec<_>>()
+                .join(", "),
+            result.transactions.len(),
+            result.gas_used,
+            result.effective_gas_price
+        );
+    }
+
+    let mut line = serde_json::to_vec(&MintResults {
+        timestamp: shared::util::epoch_time().as_secs(),
+        contract,
+        wallets: results,
+    })?;
+    line.push(b'\n');
+    tokio::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .await?
+        .write_all(&line)
+        .await?;
+    info!("results written to {}", path);
+    Ok(())
+}
+
+/// Decodes the ERC-721 and ERC-1155 transfers to `wallet` in the logs of a
+/// receipt.
+fn minted_tokens(receipt: &TransactionReceipt, wallet: Address) -> Vec<MintedToken> {
+    let transfer = H256::from(keccak256(TRANSFER_EVENT));
+    let transfer_single = H256::from(keccak256(TRANSFER_SINGLE_EVENT));
+    let transfer_batch = H256::from(keccak256(TRANSFER_BATCH_EVENT));
+    let to = H256::from(wallet);
+
+    let mut tokens = Vec::new();
+    for log in receipt.logs.iter() {
+        let token = |token_id: U256, amount: U256| MintedToken {
+            contract: log.address,
+            token_id: token_id.to_string(),
+            amount: amount.to_string(),
+        };
+
+        match log.topics.as_slice() {
+            // ERC-20 transfers have the same signature, but no indexed value
+            [topic, _, recipient, token_id] if *topic == transfer && *recipient == to => {
+                tokens.push(token(U256::from(token_id.as_bytes()), U256::one()));
+            }
+            [topic, _, _, recipient] if *topic == transfer_single && *recipient == to => {
+                if let Ok(values) = ethers::abi::decode(
+                    &[ParamType::Uint(256), ParamType::Uint(256)],
+                    log.data.as_ref(),
+                ) {
+                    if let [Token::Uint(token_id), Token::Uint(amount)] = values.as_slice() {
+                        tokens.push(token(*token_id, *amount));
+                    }
+                }
+            }
+            [topic, _, _, recipient] if *topic == transfer_batch && *recipient == to => {
+                let array = ParamType::Array(Box::new(ParamType::Uint(256)));
+                if let Ok(values) = ethers::abi::decode(&[array.clone(), array], log.data.as_ref())
+                {
+                    if let [Token::Array(token_ids), Token::Array(amounts)] = values.as_slice() {
+                        for (token_id, amount) in token_ids.iter().zip(amounts) {
+                            if let (Token::Uint(token_id), Token::Uint(amount)) = (token_id, amount)
+                            {
+                                tokens.push(token(*token_id, *amount));
+                            }
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    tokens
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn log(contract: Address, topics: Vec<H256>, data: Vec<Token>) -> Log {
+        Log {
+            address: contract,
+            topics,
+            data: ethers::abi::encode(&data).into(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn results_minted_tokens() {
+        let wallet = Address::from_low_u64_be(1);
+        let other = Address::from_low_u64_be(2);
+        let erc721 = Address::from_low_u64_be(721);
+        let erc1155 = Address::from_low_u64_be(1155);
+        let erc20 = Address::from_low_u64_be(20);
+        let topic = |event: &str| H256::from(keccak256(event));
+        let uint = |value: u64| Token::Uint(value.into());
+        let uints = |values: &[u64]| Token::Array(values.iter().map(|v| uint(*v)).collect());
+
+        let receipt = TransactionReceipt {
+            logs: vec![
+                log(
+                    erc721,
+                    vec![
+                        topic(TRANSFER_EVENT),
+                        H256::zero(),
+                        wallet.into(),
+                        H256::from_low_u64_be(7),
+                    ],
+                    vec![],
+                ),
+                // minted to another wallet
+                log(
+                    erc721,
+                    vec![
+                        topic(TRANSFER_EVENT),
+                        H256::zero(),
+                        other.into(),
+                        H256::from_low_u64_be(8),
+                    ],
+                    vec![],
+                ),
+                // an ERC-20 transfer, with the amount in the data
+                log(
+                    erc20,
+                    vec![topic(TRANSFER_EVENT), other.into(), wallet.into()],
+                    vec![uint(1_000)],
+                ),
+                log(
+                    erc1155,
+                    vec![
+                        topic(TRANSFER_SINGLE_EVENT),
+                        other.into(),
+                        H256::zero(),
+                        wallet.into(),
+                    ],
+                    vec![uint(3), uint(2)],
+                ),
+                log(
+                    erc1155,
+                    vec![
+                        topic(TRANSFER_BATCH_EVENT),
+                        other.into(),
+                        H256::zero(),
+                        wallet.into(),
+                    ],
+                    vec![uints(&[4, 5]), uints(&[1, 6])],
+                ),
+            ],
+            ..Default::default()
+        };
+
+        let tokens = minted_tokens(&receipt, wallet)
+            .into_iter()
+            .map(|token| (token.contract, token.token_id, token.amount))
+            .collect::<Vec<_>>();
+        let token = |contract: Address, token_id: &str, amount: &str| {
+            (contract, token_id.to_string(), amount.to_string())
+        };
+        assert_eq!(
+            tokens,
+            vec![
+                token(erc721, "7", "1"),
+                token(erc1155, "3", "2"),
+                token(erc1155, "4", "1"),
+                token(erc1155, "5", "6"),
+            ]
+        );
+        assert!(minted_tokens(&receipt, Address::zero()).is_empty());
+    }
+}
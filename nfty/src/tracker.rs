@@ -0,0 +1,337 @@
+use crate::{flashbots::decode_raw_transaction, Context, Error};
+use ethers::prelude::{transaction::eip2718::TypedTransaction, *};
+use ethers_core::utils::keccak256;
+use log::*;
+use shared::config::Tracker as TrackerConfig;
+use std::collections::HashMap;
+
+/// The minimum fee bump nodes accept to replace a pending transaction.
+const MINIMUM_FEE_BUMP: f64 = 0.1;
+/// The gas used by a plain transfer, such as a cancellation.
+const TRANSFER_GAS: u64 = 21_000;
+/// How many blocks transactions are tracked for at most, by default.
+const DEFAULT_MAXIMUM_BLOCKS: u64 = 25;
+
+/// A transaction that is watched until its nonce is used.
+#[derive(Debug, Clone)]
+pub struct TrackedTransaction {
+    pub wallet: Address,
+    pub nonce: U256,
+    /// The hashes of every version of the transaction that was sent, from
+    /// the oldest to the newest.
+    pub hashes: Vec<TxHash>,
+    /// The receipt of the version that was mined, if one was.
+    pub receipt: Option<TransactionReceipt>,
+    /// Whether the transaction was cancelled, and no version sent before
+    /// the cancellation was mined.
+    pub cancelled: bool,
+    request: Eip1559TransactionRequest,
+    sent_block: U64,
+    at_maximum_fee: bool,
+    done: bool,
+}
+
+/// Watches submitted transactions across blocks, and bumps the fees of the
+/// ones that stay pending for too long (if it has a config).
+pub struct TransactionTracker<'a, M, S> {
+    ctx: &'a Context<M, S>,
+    wallets: HashMap<Address, &'a LocalWallet>,
+    config: Option<&'a TrackerConfig>,
+    transactions: Vec<TrackedTransaction>,
+}
+
+impl<'a, M: 'static + Middleware + Clone, S: 'static + Signer + Clone>
+    TransactionTracker<'a, M, S>
+{
+    pub fn new(
+        ctx: &'a Context<M, S>,
+        wallets: &'a [LocalWallet],
+        config: Option<&'a TrackerConfig>,
+    ) -> Self {
+        Self {
+            ctx,
+            wallets: wallets.iter().map(|w| (w.address(), w)).collect(),
+            config,
+            transactions: Vec::new(),
+        }
+    }
+
+    /// Watches an RLP encoded signed transaction, sent at `block`.
+    pub fn push(&mut self, raw_tx: &[u8], block: U64) -> Result<&mut Self, Error> {
+        let tx = decode_raw_transaction(raw_tx).ok_or("could not decode tracked tx")?;
+        let mut request = Eip1559TransactionRequest::new()
+            .from(tx.from)
+            .value(tx.value)
+            .data(tx.input.clone())
+            .nonce(tx.nonce)
+            .gas(tx.gas)
+            .max_fee_per_gas(tx.max_fee_per_gas)
+            .max_priority_fee_per_gas(tx.max_priority_fee_per_gas.unwrap_or(tx.max_fee_per_gas));
+        if let Some(to) = tx.to {
+            request = request.to(to);
+        }
+
+        self.transactions.push(TrackedTransaction {
+            wallet: tx.from,
+            nonce: tx.nonce,
+            hashes: vec![tx.hash],
+            receipt: None,
+            cancelled: false,
+            request,
+            sent_block: block,
+            at_maximum_fee: false,
+            done: false,
+        });
+        Ok(self)
+    }
+
+    /// Watches every block until the nonces of all the transactions are
+    /// used or the pending ones are at the maximum fee, and returns them.
+    ///
+    /// Gives up on the pending transactions, e.g. ones that were dropped,
+    /// after the maximum blocks of the config.
+    pub async fn track(mut self) -> Result<Vec<TrackedTransaction>, Error> {
+        let maximum_blocks = self
+            .config
+            .and_then(|config| config.maximum_blocks)
+            .unwrap_or(DEFAULT_MAXIMUM_BLOCKS);
+        let mut blocks = self.ctx.pubsub().subscribe_blocks().await?;
+        info!("tracking {} tx(s)...", self.transactions.len());
+        let mut first_block = None;
+        while let Some(block) = blocks.next().await {
+            let block_number = block.number.unwrap_or_default();
+            let first_block = *first_block.get_or_insert(block_number);
+            let mut nonces = HashMap::new();
+            for i in 0..self.transactions.len() {
+                if self.transactions[i].done {
+                    continue;
+                }
+
+                let wallet = self.transactions[i].wallet;
+                let nonce = match nonces.get(&wallet) {
+                    Some(nonce) => *nonce,
+                    None => {
+                        let nonce = self
+                            .ctx
+                            .provider()
+                            .get_transaction_count(wallet, None)
+                            .await?;
+                        nonces.insert(wallet, nonce);
+                        nonce
+                    }
+                };
+
+                if nonce > self.transactions[i].nonce {
+                    self.settle(i).await?;
+                } else if let Some(config) = self.config {
+                    if block_number >= self.transactions[i].sent_block + config.blocks {
+                        self.bump(i, block_number, config).await?;
+                    }
+                }
+            }
+
+            if self
+                .transactions
+                .iter()
+                .all(|tx| tx.done || tx.at_maximum_fee)
+            {
+                return Ok(self.transactions);
+            }
+            if block_number >= first_block + maximum_blocks {
+                warn!(
+                    "{} tx(s) still pending after {} blocks, giving up",
+                    self.transactions.iter().filter(|tx| !tx.done).count(),
+                    maximum_blocks
+                );
+                return Ok(self.transactions);
+            }
+        }
+
+        Err("error subscribing to blocks".into())
+    }
+
+    /// Replaces the transaction at `index` with a zero-value transfer to its
+    /// own wallet, at the same nonce.
+    ///
+    /// The cancellation pays the bumped fees even above the maximum fee of
+    /// the tracker, since it only uses 21000 gas.
+    pub async fn cancel(&mut self, index: usize, block: U64) -> Result<(), Error> {
+        let tracked = &self.transactions[index];
+        let (max_fee, priority_fee) = bump_fees(&tracked.request, self.fee_bump());
+        let request = Eip1559TransactionRequest::new()
+            .from(tracked.wallet)
+            .to(tracked.wallet)
+            .value(0)
+            .nonce(tracked.nonce)
+            .gas(TRANSFER_GAS)
+            .max_fee_per_gas(max_fee)
+            .max_priority_fee_per_gas(priority_fee);
+
+        info!(
+            "cancelling tx 0x{:x} at nonce {}",
+            tracked.hashes.last().cloned().unwrap_or_default(),
+            tracked.nonce
+        );
+        if self.send(index, request, block).await? {
+            self.transactions[index].cancelled = true;
+        }
+        Ok(())
+    }
+
+    fn fee_bump(&self) -> f64 {
+        self.config
+            .and_then(|config| config.fee_bump)
+            .unwrap_or(MINIMUM_FEE_BUMP)
+            .max(MINIMUM_FEE_BUMP)
+    }
+
+    /// Re-signs the transaction at `index` with bumped fees, or cancels it
+    /// if the bumped fees are above the maximum fee. Cancellations are bumped
+    /// the same way, up to the maximum fee.
+    async fn bump(
+        &mut self,
+        index: usize,
+        block: U64,
+        config: &TrackerConfig,
+    ) -> Result<(), Error> {
+        let tracked = &self.transactions[index];
+        if tracked.at_maximum_fee {
+            return Ok(());
+        }
+
+        let (max_fee, priority_fee) = bump_fees(&tracked.request, self.fee_bump());
+        if max_fee > U256::from((config.maximum_fee * 1e9) as u128) {
+            if config.cancel.unwrap_or(false) && !tracked.cancelled {
+                return self.cancel(index, block).await;
+            }
+            warn!(
+                "tx 0x{:x} is still pending at the maximum fee",
+                tracked.hashes.last().cloned().unwrap_or_default()
+            );
+            self.transactions[index].at_maximum_fee = true;
+            return Ok(());
+        }
+
+        info!(
+            "tx 0x{:x} is still pending, bumping max fee to {} gwei",
+            tracked.hashes.last().cloned().unwrap_or_default(),
+            max_fee.as_u128() as f64 / 1e9
+        );
+        let request = tracked
+            .request
+            .clone()
+            .max_fee_per_gas(max_fee)
+            .max_priority_fee_per_gas(priority_fee);
+        self.send(index, request, block).await?;
+        Ok(())
+    }
+
+    /// Signs and sends a new version of the transaction at `index`. Returns
+    /// whether it was accepted.
+    async fn send(
+        &mut self,
+        index: usize,
+        request: Eip1559TransactionRequest,
+        block: U64,
+    ) -> Result<bool, Error> {
+        let wallet = self.wallets[&self.transactions[index].wallet];
+        let tx = TypedTransaction::Eip1559(request.clone());
+        let signature = wallet.sign_transaction(&tx).await?;
+        let raw_tx = tx.rlp_signed(wallet.chain_id(), &signature);
+
+        let tracked = &mut self.transactions[index];
+        match self
+            .ctx
+            .provider()
+            .send_raw_transaction(raw_tx.clone())
+            .await
+        {
+            Ok(_) => {
+                tracked.hashes.push(keccak256(&raw_tx).into());
+                tracked.request = request;
+                tracked.sent_block = block;
+                Ok(true)
+            }
+            Err(e) => {
+                warn!("error replacing tx at nonce {}: {}", tracked.nonce, e);
+                Ok(false)
+            }
+        }
+    }
+
+    /// Looks for the version of the transaction at `index` that was mined,
+    /// once its nonce is used.
+    async fn settle(&mut self, index: usize) -> Result<(), Error> {
+        let tracked = &mut self.transactions[index];
+        tracked.done = true;
+        for tx_hash in tracked.hashes.iter().rev() {
+            if let Some(receipt) = self
+                .ctx
+                .provider()
+                .get_transaction_receipt(*tx_hash)
+                .await?
+            {
+                info!(
+                    "0x{:x}: tx 0x{:x} at nonce {} mined in block {}",
+                    tracked.wallet,
+                    tx_hash,
+                    tracked.nonce,
+                    receipt.block_number.unwrap_or_default()
+                );
+                // the cancellation is always the last version
+                if Some(tx_hash) != tracked.hashes.last() {
+                    tracked.cancelled = false;
+                }
+                tracked.receipt = Some(receipt);
+                return Ok(());
+            }
+        }
+
+        warn!(
+            "0x{:x}: nonce {} was used by another tx",
+            tracked.wallet, tracked.nonce
+        );
+        Ok(())
+    }
+}
+
+/// Bumps both fees of a transaction by `bump`, rounding up.
+fn bump_fees(request: &Eip1559TransactionRequest, bump: f64) -> (U256, U256) {
+    let bump = U256::from((bump * 10_000.) as u64);
+    let bumped = |fee: Option<U256>| {
+        let fee = fee.unwrap_or_default();
+        fee + (fee * bump + 9_999) / 10_000
+    };
+    (
+        bumped(request.max_fee_per_gas),
+        bumped(request.max_priority_fee_per_gas),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tracker_bump_fees() {
+        for (max_fee, priority_fee) in [(100u64, 1u64), (15, 15), (1_000_000_001, 7), (0, 0)] {
+            let request = Eip1559TransactionRequest::new()
+                .max_fee_per_gas(max_fee)
+                .max_priority_fee_per_gas(priority_fee);
+            let (bumped_max_fee, bumped_priority_fee) = bump_fees(&request, MINIMUM_FEE_BUMP);
+            // nodes reject replacements that bump either fee by less than 10%
+            assert!(bumped_max_fee * 10 >= U256::from(max_fee) * 11);
+            assert!(bumped_priority_fee * 10 >= U256::from(priority_fee) * 11);
+        }
+
+        let request = Eip1559TransactionRequest::new()
+            .max_fee_per_gas(15)
+            .max_priority_fee_per_gas(1);
+        // 16.5 and 1.1 are rounded up
+        assert_eq!(
+            bump_fees(&request, MINIMUM_FEE_BUMP),
+            (U256::from(17), U256::from(2))
+        );
+        assert_eq!(bump_fees(&request, 0.5), (U256::from(23), U256::from(2)));
+    }
+}
@@ -1,15 +1,27 @@
 use crate::{
+    flashbots::{CoinbasePayment, MevShareBundle, PrivacyHint, Relay, RelayAuth, SignaturePayload},
     model::{AtomicMatchArgs, AtomicOrder, AtomicSig, OldOrder},
     opensea::Order,
 };
 use ethers::{
     abi::{Token, Uint},
-    prelude::{transaction::eip2718::TypedTransaction, *},
+    prelude::{
+        transaction::{eip2718::TypedTransaction, eip2930::AccessList},
+        *,
+    },
 };
 use log::*;
 use rand::{thread_rng, Rng};
-use shared::{config::Config as NftyConfig, contracts, util};
-use std::{convert::TryInto, str::FromStr};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use serde::{Deserialize, Serialize};
+use shared::{
+    config::{
+        CoinbasePayment as CoinbasePaymentConfig, Config as NftyConfig, MevShareHint, Mint,
+        Relay as RelayConfig, RelayAuth as RelayAuthConfig, RelaySignaturePayload,
+    },
+    contracts, util,
+};
+use std::{convert::TryInto, str::FromStr, time::Duration};
 
 const NFTY_TAG: [u8; 32] = [
     0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
@@ -19,6 +31,7 @@ const NFTY_TAG: [u8; 32] = [
 const OPENSEA_CONTRACT: &str = "0x7be8076f4ea4a4ad08075c2508e481d6c946d12b";
 pub const NULL_ADDR: Address = H160([0u8; 20]);
 pub const SALE_SIDE_BUY: u8 = 0;
+const COINBASE_PAYMENT_FUNCTION: &str = "pay()";
 
 // TODO: refactor, too lazy rn
 fn shift_mask(mask: &[u8]) -> Vec<u8> {
@@ -267,6 +280,9 @@ pub async fn order_to_tx<M: 'static + Middleware, S: 'static + Signer>(
         U256::from(opensea_config.gas_limit)
     };
     tx.set_gas(gas_amount);
+    if opensea_config.access_list.unwrap_or(false) {
+        attach_access_list(provider, &mut tx).await;
+    }
 
     Ok(tx)
 }
@@ -467,6 +483,231 @@ pub async fn new_order_to_tx<M: 'static + Middleware, S: 'static + Signer>(
         U256::from(opensea_config.gas_limit)
     };
     tx.set_gas(gas_amount);
+    if opensea_config.access_list.unwrap_or(false) {
+        attach_access_list(provider, &mut tx).await;
+    }
 
     Ok(tx)
 }
+
+// `Serialize` is required by `Provider::request`.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AccessListWithGasUsed {
+    access_list: AccessList,
+    gas_used: U256,
+}
+
+/// Attaches the access list created by `eth_createAccessList` to an EIP-1559
+/// transaction, if it lowers the gas used by the transaction.
+///
+/// The transaction is left as is if the access list could not be created,
+/// e.g. because the transaction reverts.
+pub async fn attach_access_list<M: Middleware>(provider: &M, tx: &mut TypedTransaction) {
+    if let Some(access_list) = create_access_list(provider, tx).await {
+        set_access_list(tx, access_list);
+    }
+}
+
+/// The access list created by `eth_createAccessList` for an EIP-1559
+/// transaction, or `None` if it could not be created or would not lower the
+/// gas used by the transaction.
+///
+/// Transactions with the same calldata can share the access list, see
+/// [`set_access_list`].
+pub async fn create_access_list<M: Middleware>(
+    provider: &M,
+    tx: &TypedTransaction,
+) -> Option<AccessList> {
+    if !matches!(tx, TypedTransaction::Eip1559(_)) {
+        return None;
+    }
+
+    let gas_used = match provider.estimate_gas(tx).await {
+        Ok(gas_used) => gas_used,
+        Err(e) => {
+            warn!("error estimating gas for access list: {}", e);
+            return None;
+        }
+    };
+    let created: AccessListWithGasUsed = match provider
+        .provider()
+        .request("eth_createAccessList", (tx, "latest"))
+        .await
+    {
+        Ok(created) => created,
+        Err(e) => {
+            warn!("error creating access list: {}", e);
+            return None;
+        }
+    };
+
+    if created.gas_used >= gas_used {
+        debug!(
+            "access list would not save gas ({} >= {})",
+            created.gas_used, gas_used
+        );
+        return None;
+    }
+
+    info!(
+        "created access list with {} addresses, saving {} gas",
+        created.access_list.0.len(),
+        gas_used - created.gas_used
+    );
+    Some(created.access_list)
+}
+
+/// Sets the access list of an EIP-1559 transaction.
+pub fn set_access_list(tx: &mut TypedTransaction, access_list: AccessList) {
+    if let TypedTransaction::Eip1559(inner) = tx {
+        inner.access_list = access_list;
+    }
+}
+
+/// Builds a MEV-Share bundle for a single signed transaction, targeting
+/// `blocks` blocks starting at `block`, with the hints, builders and refund
+/// from the OpenSea config.
+pub fn mev_share_bundle(
+    config: &NftyConfig,
+    tx: Bytes,
+    block: U64,
+    blocks: u64,
+) -> Result<MevShareBundle, shared::Error> {
+    let mut bundle = MevShareBundle::new();
+    bundle
+        .push_transaction(tx)
+        .set_block(block)
+        .set_max_block(block + blocks.max(1) - 1);
+
+    let mev_share_config = match config.opensea.as_ref().and_then(|o| o.mev_share.as_ref()) {
+        Some(mev_share_config) => mev_share_config,
+        None => return Ok(bundle),
+    };
+
+    for hint in mev_share_config.hints.iter().flatten() {
+        bundle.push_hint(match hint {
+            MevShareHint::Calldata => PrivacyHint::Calldata,
+            MevShareHint::ContractAddress => PrivacyHint::ContractAddress,
+            MevShareHint::Logs => PrivacyHint::Logs,
+            MevShareHint::FunctionSelector => PrivacyHint::FunctionSelector,
+            MevShareHint::Hash => PrivacyHint::Hash,
+            MevShareHint::TxHash => PrivacyHint::TxHash,
+        });
+    }
+    if let Some(builders) = mev_share_config.builders.clone() {
+        bundle.set_builders(builders);
+    }
+    if let Some(percent) = mev_share_config.refund_percent {
+        bundle.push_refund(0, percent);
+    }
+    if let Some(address) = mev_share_config
+        .refund_address
+        .as_ref()
+        .filter(|a| !a.is_empty())
+    {
+        bundle.push_refund_address(Address::from_str(address)?, 100);
+    }
+
+    Ok(bundle)
+}
+
+/// Builds a relay client from its config, with its own authentication,
+/// headers, timeout, proxy and supported methods.
+pub fn relay<S: Signer>(config: &RelayConfig, signer: S) -> Result<Relay<S>, shared::Error> {
+    let mut headers = HeaderMap::new();
+    for (name, value) in config.headers.iter().flatten() {
+        headers.insert(HeaderName::from_str(name)?, HeaderValue::from_str(value)?);
+    }
+
+    let mut client = reqwest::Client::builder().default_headers(headers);
+    if let Some(timeout) = config.timeout {
+        client = client.timeout(Duration::from_millis(timeout));
+    }
+    if let Some(proxy_url) = config.proxy_url.as_ref().filter(|p| !p.is_empty()) {
+        client = client.proxy(reqwest::Proxy::all(proxy_url)?);
+    }
+
+    let auth_header = config.auth_header.clone().filter(|h| !h.is_empty());
+    let auth = match config.auth.as_ref().unwrap_or(&RelayAuthConfig::Flashbots) {
+        RelayAuthConfig::None => RelayAuth::None,
+        RelayAuthConfig::Flashbots => RelayAuth::flashbots(),
+        RelayAuthConfig::ApiKey => RelayAuth::ApiKey {
+            header: auth_header.unwrap_or_else(|| "X-Api-Key".into()),
+            key: config
+                .api_key
+                .clone()
+                .filter(|k| !k.is_empty())
+                .ok_or_else(|| format!("relay {} requires an api_key", config.url))?,
+        },
+        RelayAuthConfig::Signature => RelayAuth::Signature {
+            header: auth_header.unwrap_or_else(|| "X-Flashbots-Signature".into()),
+            payload: match config.signature_payload {
+                Some(RelaySignaturePayload::Body) => SignaturePayload::Body,
+                _ => SignaturePayload::BodyHash,
+            },
+        },
+    };
+
+    let mut relay = Relay::new(url::Url::parse(&config.url)?, signer);
+    relay.set_client(client.build()?).set_auth(auth);
+    if let Some(methods) = config.methods.clone() {
+        relay.set_methods(methods);
+    }
+    Ok(relay)
+}
+
+/// Builds the coinbase payment of bundles from its config, or `None` if
+/// no payment contract is set.
+pub fn coinbase_payment(
+    config: &CoinbasePaymentConfig,
+) -> Result<Option<CoinbasePayment>, shared::Error> {
+    match config.contract_address.as_ref().filter(|a| !a.is_empty()) {
+        Some(address) => Ok(Some(CoinbasePayment {
+            address: Address::from_str(address)?,
+            data: contracts::encode_call(
+                config
+                    .function
+                    .as_deref()
+                    .unwrap_or(COINBASE_PAYMENT_FUNCTION),
+                Vec::<Token>::new().as_slice(),
+            )
+            .into(),
+        })),
+        None => Ok(None),
+    }
+}
+
+/// The value of the coinbase payment: the fixed value of the config, plus
+/// its share of the smart gas `budget`. The rest of the budget is left for
+/// the priority fee.
+pub fn coinbase_payment_value(config: &CoinbasePaymentConfig, budget: U256) -> U256 {
+    let share = config.share.unwrap_or(0.).clamp(0., 1.);
+    U256::from(config.value.unwrap_or(0.) as u128)
+        + budget * U256::from((share * 10_000.) as u64) / 10_000
+}
+
+/// The wallets a mint signs for: the account wallet first, followed by the
+/// wallets of the config and of its wallets file. Duplicates are skipped.
+pub async fn mint_wallets(
+    private_key: &str,
+    config: &Mint,
+    chain_id: u64,
+) -> Result<Vec<LocalWallet>, shared::Error> {
+    let mut private_keys = vec![private_key.to_string()];
+    private_keys.extend(config.wallets.iter().flatten().cloned());
+    if let Some(path) = config.wallets_file.as_ref().filter(|p| !p.is_empty()) {
+        private_keys.extend(serde_json::from_slice::<Vec<String>>(
+            &tokio::fs::read(path).await?,
+        )?);
+    }
+
+    let mut wallets: Vec<LocalWallet> = Vec::with_capacity(private_keys.len());
+    for private_key in private_keys.iter().filter(|k| !k.is_empty()) {
+        let wallet = LocalWallet::from_str(private_key)?.with_chain_id(chain_id);
+        if !wallets.iter().any(|w| w.address() == wallet.address()) {
+            wallets.push(wallet);
+        }
+    }
+    Ok(wallets)
+}
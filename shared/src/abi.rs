@@ -0,0 +1,317 @@
+use crate::{
+    config::MintArgument,
+    contracts,
+    token::{parse_uint, Token as ArgumentType},
+    util::decode_hex,
+};
+use ethers::{
+    abi::{Abi, Function, ParamType, Token},
+    prelude::{Address, I256, U256},
+};
+use std::{mem, str::FromStr};
+use toml::Value;
+
+/// A function of an ABI, with arguments converted to the types of its
+/// inputs.
+#[derive(Clone, Debug)]
+pub struct Call {
+    pub function: Function,
+    pub arguments: Vec<Token>,
+}
+
+impl Call {
+    /// The calldata of the call.
+    pub fn encode(&self) -> Result<Vec<u8>, crate::Error> {
+        Ok(self.function.encode_input(&self.arguments)?)
+    }
+
+    /// Decodes the values returned by the call with the outputs of the
+    /// function.
+    pub fn decode(&self, response: &[u8]) -> Result<Vec<Token>, crate::Error> {
+        Ok(self.function.decode_output(response)?)
+    }
+}
+
+/// Loads a standard ABI JSON file, e.g. the `abi` of a Hardhat artifact or
+/// the ABI shown on Etherscan.
+pub fn load(path: &str) -> Result<Abi, crate::Error> {
+    let json = std::fs::read(path).map_err(|e| format!("could not read {}: {}", path, e))?;
+    serde_json::from_slice(&json).map_err(|e| format!("invalid ABI in {}: {}", path, e).into())
+}
+
+/// Loads the ABI at the `abi_path` of a config, or returns `None` if it has
+/// none.
+///
+/// ABIs are meant to be loaded once per task, and passed to [`encode_call`]
+/// for every call.
+pub fn load_optional(abi_path: Option<&String>) -> Result<Option<Abi>, crate::Error> {
+    abi_path
+        .filter(|path| !path.is_empty())
+        .map(|path| load(path))
+        .transpose()
+}
+
+/// Finds the function of a call in the ABI, and converts the arguments to
+/// the types of its inputs.
+///
+/// `function` is either a name, in which case overloads are resolved by the
+/// number and the types of the arguments, or a full signature such as
+/// `mint(address,uint256)`.
+pub fn resolve(
+    abi: &Abi,
+    function: &str,
+    arguments: &[MintArgument],
+) -> Result<Call, crate::Error> {
+    let name = function.split('(').next().unwrap_or_default();
+    let candidates = abi
+        .functions_by_name(name)
+        .map_err(|_| format!("the ABI has no function named {}", name))?
+        .iter()
+        .filter(|candidate| !function.contains('(') || signature(candidate) == function)
+        .filter(|candidate| candidate.inputs.len() == arguments.len())
+        .collect::<Vec<_>>();
+
+    let mut calls = Vec::new();
+    let mut error = None;
+    for candidate in candidates {
+        let kinds = candidate
+            .inputs
+            .iter()
+            .map(|input| &input.kind)
+            .collect::<Vec<_>>();
+        match convert(&kinds, arguments) {
+            Ok(arguments) => calls.push(Call {
+                function: candidate.clone(),
+                arguments,
+            }),
+            Err(e) => error = Some(format!("{}: {}", signature(candidate), e)),
+        }
+    }
+
+    match calls.len() {
+        1 => Ok(calls.remove(0)),
+        0 => Err(error
+            .unwrap_or_else(|| {
+                format!(
+                    "no function {} takes {} argument(s)",
+                    function,
+                    arguments.len()
+                )
+            })
+            .into()),
+        _ => Err(format!(
+            "{} is ambiguous, use one of: {}",
+            function,
+            calls
+                .iter()
+                .map(|call| signature(&call.function))
+                .collect::<Vec<_>>()
+                .join(", ")
+        )
+        .into()),
+    }
+}
+
+/// The calldata of a call, resolved in the ABI if there is one, or encoded
+/// from the signature of the function otherwise.
+pub fn encode_call(
+    abi: Option<&Abi>,
+    function: &str,
+    arguments: &[MintArgument],
+) -> Result<Vec<u8>, crate::Error> {
+    match abi {
+        Some(abi) => resolve(abi, function, arguments)?.encode(),
+        None => Ok(contracts::encode_call(
+            function,
+            arguments
+                .iter()
+                .map(|x| x.r#type.to_token(&x.value))
+                .collect::<Result<Vec<_>, _>>()?
+                .as_slice(),
+        )),
+    }
+}
+
+/// Converts values to the given types, checking them against the types they
+/// are declared with.
+pub fn convert(kinds: &[&ParamType], values: &[MintArgument]) -> Result<Vec<Token>, crate::Error> {
+    if kinds.len() != values.len() {
+        return Err(format!("expected {} value(s), got {}", kinds.len(), values.len()).into());
+    }
+
+    kinds
+        .iter()
+        .zip(values)
+        .enumerate()
+        .map(|(i, (kind, value))| {
+            if mem::discriminant(&value.r#type) != mem::discriminant(&argument_type(kind)) {
+                return Err(format!(
+                    "value {} is declared {:?}, the ABI expects {}",
+                    i, value.r#type, kind
+                )
+                .into());
+            }
+            tokenize(kind, &value.value)
+        })
+        .collect()
+}
+
+/// The canonical signature of a function, e.g. `mint(address,uint256)`.
+fn signature(function: &Function) -> String {
+    format!(
+        "{}({})",
+        function.name,
+        function
+            .inputs
+            .iter()
+            .map(|input| input.kind.to_string())
+            .collect::<Vec<_>>()
+            .join(",")
+    )
+}
+
+fn argument_type(kind: &ParamType) -> ArgumentType {
+    match kind {
+        ParamType::Address => ArgumentType::Address,
+        ParamType::Bytes => ArgumentType::Bytes,
+        ParamType::Int(_) => ArgumentType::Int,
+        ParamType::Uint(_) => ArgumentType::Uint,
+        ParamType::Bool => ArgumentType::Bool,
+        ParamType::String => ArgumentType::String,
+        ParamType::Array(_) => ArgumentType::Array,
+        ParamType::FixedBytes(_) => ArgumentType::FixedBytes,
+        ParamType::FixedArray(_, _) => ArgumentType::FixedArray,
+        ParamType::Tuple(_) => ArgumentType::Tuple,
+    }
+}
+
+fn tokenize(kind: &ParamType, value: &Value) -> Result<Token, crate::Error> {
+    // the elements of arrays and tuples may be nested `{ type, value }`
+    // arguments, as without an ABI
+    if let Value::Table(table) = value {
+        if let Some(value) = table.get("value") {
+            return tokenize(kind, value);
+        }
+    }
+
+    let invalid = || format!("{} is not a valid {}", value, kind);
+    Ok(match (kind, value) {
+        (ParamType::Address, Value::String(s)) => Token::Address(Address::from_str(s)?),
+        (ParamType::Uint(_), Value::Integer(i)) if *i >= 0 => Token::Uint(U256::from(*i)),
+        (ParamType::Uint(_), Value::Float(f)) if *f >= 0. => Token::Uint(U256::from(*f as u128)),
+        // strings are hex, as without an ABI
+        (ParamType::Uint(_), Value::String(s)) => Token::Uint(parse_uint(s)?),
+        (ParamType::Int(_), Value::Integer(i)) => Token::Int(I256::from(*i).into_raw()),
+        (ParamType::Int(_), Value::String(s)) => Token::Int(I256::from_dec_str(s)?.into_raw()),
+        (ParamType::Bool, Value::Boolean(b)) => Token::Bool(*b),
+        (ParamType::String, Value::String(s)) => Token::String(s.clone()),
+        (ParamType::Bytes, Value::String(s)) => Token::Bytes(decode_hex(s)?),
+        (ParamType::FixedBytes(len), Value::String(s)) => {
+            let bytes = decode_hex(s)?;
+            if bytes.len() != *len {
+                return Err(invalid().into());
+            }
+            Token::FixedBytes(bytes)
+        }
+        (ParamType::Array(kind), Value::Array(values)) => Token::Array(
+            values
+                .iter()
+                .map(|value| tokenize(kind, value))
+                .collect::<Result<_, _>>()?,
+        ),
+        (ParamType::FixedArray(kind, len), Value::Array(values)) if values.len() == *len => {
+            Token::FixedArray(
+                values
+                    .iter()
+                    .map(|value| tokenize(kind, value))
+                    .collect::<Result<_, _>>()?,
+            )
+        }
+        (ParamType::Tuple(kinds), Value::Array(values)) if values.len() == kinds.len() => {
+            Token::Tuple(
+                kinds
+                    .iter()
+                    .zip(values)
+                    .map(|(kind, value)| tokenize(kind, value))
+                    .collect::<Result<_, _>>()?,
+            )
+        }
+        _ => return Err(invalid().into()),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const ABI: &str = r#"[
+        {"type": "function", "name": "mint", "stateMutability": "payable", "outputs": [],
+         "inputs": [{"name": "quantity", "type": "uint256"}]},
+        {"type": "function", "name": "mint", "stateMutability": "payable", "outputs": [],
+         "inputs": [{"name": "to", "type": "address"}, {"name": "quantity", "type": "uint256"}]},
+        {"type": "function", "name": "mint", "stateMutability": "payable", "outputs": [],
+         "inputs": [{"name": "to", "type": "address"}, {"name": "data", "type": "bytes"}]}
+    ]"#;
+
+    fn argument(r#type: ArgumentType, value: Value) -> MintArgument {
+        MintArgument { r#type, value }
+    }
+
+    #[test]
+    fn resolve_overloads() {
+        let abi: Abi = serde_json::from_str(ABI).unwrap();
+        let to = Value::String("0x000000000000000000000000000000000000dEaD".into());
+
+        let call = resolve(
+            &abi,
+            "mint",
+            &[argument(ArgumentType::Uint, Value::Integer(2))],
+        )
+        .unwrap();
+        assert_eq!(signature(&call.function), "mint(uint256)");
+        assert_eq!(
+            call.encode().unwrap(),
+            contracts::encode_call("mint(uint256)", &[Token::Uint(2.into())][..])
+        );
+
+        let call = resolve(
+            &abi,
+            "mint",
+            &[
+                argument(ArgumentType::Address, to.clone()),
+                argument(ArgumentType::Uint, Value::String("3".into())),
+            ],
+        )
+        .unwrap();
+        assert_eq!(signature(&call.function), "mint(address,uint256)");
+        assert_eq!(call.arguments[1], Token::Uint(3.into()));
+
+        // the declared type must match the ABI
+        assert!(resolve(
+            &abi,
+            "mint(address,bytes)",
+            &[
+                argument(ArgumentType::Address, to),
+                argument(ArgumentType::Uint, Value::Integer(3)),
+            ],
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn encode_with_and_without_abi() {
+        let abi: Abi = serde_json::from_str(ABI).unwrap();
+        for value in ["10", "0x10"] {
+            let arguments = [argument(ArgumentType::Uint, Value::String(value.into()))];
+            let calldata = encode_call(Some(&abi), "mint", &arguments).unwrap();
+            assert_eq!(
+                calldata,
+                encode_call(None, "mint(uint256)", &arguments).unwrap()
+            );
+            assert_eq!(
+                calldata,
+                contracts::encode_call("mint(uint256)", &[Token::Uint(16.into())][..])
+            );
+        }
+    }
+}
@@ -0,0 +1,325 @@
+use crate::config::{EventTrigger, MintArgument, StateCheckOperator, StateChecks, StateChecksMode};
+use ethers::{
+    abi::{self, Event, ParamType, RawLog, Token},
+    prelude::{Log, H256, I256},
+};
+use std::cmp::Ordering;
+
+/// Decodes the result of the call of a state check, and compares the returned
+/// values with the expected ones using the operator of the check.
+///
+/// The result is decoded with the outputs of the function if the check has
+/// an ABI (loaded from its `abi_path`), or with the types of the expected
+/// values otherwise. Every returned value must satisfy the operator, except
+/// for `NotEqual`, which passes if any of them differs.
+pub fn check_state(
+    check: &StateChecks,
+    abi: Option<&abi::Abi>,
+    response: &[u8],
+) -> Result<bool, crate::Error> {
+    let (returned, expected, maximum) = match abi {
+        Some(abi) => {
+            let call = crate::abi::resolve(abi, &check.function, &check.arguments)?;
+            let outputs = call
+                .function
+                .outputs
+                .iter()
+                .map(|output| &output.kind)
+                .collect::<Vec<_>>();
+            (
+                call.decode(response)?,
+                crate::abi::convert(&outputs, &check.return_value)?,
+                check
+                    .maximum_value
+                    .as_ref()
+                    .map(|maximum| crate::abi::convert(&outputs, maximum))
+                    .transpose()?,
+            )
+        }
+        None => {
+            let expected = tokens(&check.return_value)?;
+            let types = expected.iter().map(param_type).collect::<Vec<_>>();
+            (
+                abi::decode(&types, response)?,
+                expected,
+                check.maximum_value.as_deref().map(tokens).transpose()?,
+            )
+        }
+    };
+
+    let operator = check.operator.clone().unwrap_or(StateCheckOperator::Equal);
+    match operator {
+        StateCheckOperator::Equal => Ok(returned == expected),
+        StateCheckOperator::NotEqual => Ok(returned != expected),
+        StateCheckOperator::Between => {
+            let maximum = maximum.ok_or("Between state checks need a maximum_value")?;
+            if maximum.len() != expected.len() {
+                return Err("maximum_value and return_value have different lengths".into());
+            }
+
+            for ((value, min), max) in returned.iter().zip(&expected).zip(&maximum) {
+                if compare(value, min)? == Ordering::Less
+                    || compare(value, max)? == Ordering::Greater
+                {
+                    return Ok(false);
+                }
+            }
+            Ok(true)
+        }
+        _ => {
+            for (value, expected) in returned.iter().zip(&expected) {
+                let ordering = compare(value, expected)?;
+                let passed = match operator {
+                    StateCheckOperator::Greater => ordering == Ordering::Greater,
+                    StateCheckOperator::GreaterOrEqual => ordering != Ordering::Less,
+                    StateCheckOperator::Less => ordering == Ordering::Less,
+                    _ => ordering != Ordering::Greater,
+                };
+                if !passed {
+                    return Ok(false);
+                }
+            }
+            Ok(true)
+        }
+    }
+}
+
+/// Combines the results of several state checks, all of them must pass by
+/// default.
+pub fn combine(mode: Option<&StateChecksMode>, results: &[bool]) -> bool {
+    match mode {
+        Some(StateChecksMode::Or) => results.iter().any(|passed| *passed),
+        _ => results.iter().all(|passed| *passed),
+    }
+}
+
+/// Matches logs against the event of an event trigger, and the values it
+/// expects the event to have.
+#[derive(Clone, Debug)]
+pub struct EventMatcher {
+    event: Event,
+    values: Vec<(usize, Token)>,
+}
+
+impl EventMatcher {
+    pub fn new(trigger: &EventTrigger) -> Result<Self, crate::Error> {
+        let abi = abi::parse_abi(&[format!("event {}", trigger.event).as_str()])?;
+        let mut event = abi
+            .events()
+            .next()
+            .cloned()
+            .ok_or_else(|| format!("invalid event signature: {}", trigger.event))?;
+        // `parse_log` decodes the values by name, the params of a signature
+        // have none and would all get the last value
+        for (i, input) in event.inputs.iter_mut().enumerate() {
+            input.name = format!("p{}", i);
+        }
+
+        let values = trigger
+            .values
+            .iter()
+            .flatten()
+            .map(|value| Ok((value.index, value.r#type.to_token(&value.value)?)))
+            .collect::<Result<Vec<_>, crate::Error>>()?;
+        if let Some((index, _)) = values.iter().find(|(i, _)| *i >= event.inputs.len()) {
+            return Err(format!("{} has no value at index {}", trigger.event, index).into());
+        }
+
+        Ok(Self { event, values })
+    }
+
+    /// The first topic of the logs of the event.
+    pub fn topic(&self) -> H256 {
+        self.event.signature()
+    }
+
+    /// Whether the log is of the event and has the expected values.
+    pub fn matches(&self, log: &Log) -> bool {
+        let decoded = match self.event.parse_log(RawLog {
+            topics: log.topics.clone(),
+            data: log.data.to_vec(),
+        }) {
+            Ok(decoded) => decoded,
+            Err(_) => return false,
+        };
+
+        self.values
+            .iter()
+            .all(|(index, value)| decoded.params[*index].value == *value)
+    }
+}
+
+fn tokens(values: &[MintArgument]) -> Result<Vec<Token>, crate::Error> {
+    values.iter().map(|x| x.r#type.to_token(&x.value)).collect()
+}
+
+fn param_type(token: &Token) -> ParamType {
+    match token {
+        Token::Address(_) => ParamType::Address,
+        Token::FixedBytes(bytes) => ParamType::FixedBytes(bytes.len()),
+        Token::Bytes(_) => ParamType::Bytes,
+        Token::Int(_) => ParamType::Int(256),
+        Token::Uint(_) => ParamType::Uint(256),
+        Token::Bool(_) => ParamType::Bool,
+        Token::String(_) => ParamType::String,
+        Token::FixedArray(tokens) => ParamType::FixedArray(
+            Box::new(
+                tokens
+                    .first()
+                    .map(param_type)
+                    .unwrap_or(ParamType::Uint(256)),
+            ),
+            tokens.len(),
+        ),
+        Token::Array(tokens) => ParamType::Array(Box::new(
+            tokens
+                .first()
+                .map(param_type)
+                .unwrap_or(ParamType::Uint(256)),
+        )),
+        Token::Tuple(tokens) => ParamType::Tuple(tokens.iter().map(param_type).collect()),
+    }
+}
+
+fn compare(value: &Token, expected: &Token) -> Result<Ordering, crate::Error> {
+    match (value, expected) {
+        (Token::Uint(a), Token::Uint(b)) => Ok(a.cmp(b)),
+        (Token::Int(a), Token::Int(b)) => Ok(I256::from_raw(*a).cmp(&I256::from_raw(*b))),
+        _ => Err(format!("cannot order {:?} and {:?}", value, expected).into()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{config::EventValue, contracts, token::Token as ArgumentType};
+    use toml::Value;
+
+    fn state_check(
+        return_value: Vec<i64>,
+        operator: StateCheckOperator,
+        maximum_value: Option<Vec<i64>>,
+    ) -> StateChecks {
+        let arguments = |values: Vec<i64>| {
+            values
+                .into_iter()
+                .map(|value| MintArgument {
+                    r#type: ArgumentType::Uint,
+                    value: Value::Integer(value),
+                })
+                .collect()
+        };
+
+        StateChecks {
+            address: None,
+            abi_path: None,
+            function: "totalSupply()".into(),
+            arguments: Vec::new(),
+            return_value: arguments(return_value),
+            operator: Some(operator),
+            maximum_value: maximum_value.map(arguments),
+        }
+    }
+
+    #[test]
+    fn state_check_operators() {
+        let response = contracts::encode_args(&[Token::Uint(5.into())][..]);
+
+        let passes = |check: StateChecks| check_state(&check, None, &response).unwrap();
+        assert!(passes(state_check(
+            vec![5],
+            StateCheckOperator::Equal,
+            None
+        )));
+        assert!(passes(state_check(
+            vec![4],
+            StateCheckOperator::NotEqual,
+            None
+        )));
+        assert!(passes(state_check(
+            vec![4],
+            StateCheckOperator::Greater,
+            None
+        )));
+        assert!(passes(state_check(
+            vec![5],
+            StateCheckOperator::GreaterOrEqual,
+            None
+        )));
+        assert!(!passes(state_check(
+            vec![5],
+            StateCheckOperator::Less,
+            None
+        )));
+        assert!(passes(state_check(
+            vec![5],
+            StateCheckOperator::LessOrEqual,
+            None
+        )));
+        assert!(passes(state_check(
+            vec![1],
+            StateCheckOperator::Between,
+            Some(vec![5])
+        )));
+        assert!(!passes(state_check(
+            vec![6],
+            StateCheckOperator::Between,
+            Some(vec![10])
+        )));
+
+        let mut check = state_check(vec![5], StateCheckOperator::Greater, None);
+        check.return_value[0].r#type = ArgumentType::Bool;
+        check.return_value[0].value = Value::Boolean(true);
+        assert!(check_state(&check, None, &response).is_err());
+    }
+
+    #[test]
+    fn event_trigger_values() {
+        let matcher = EventMatcher::new(&EventTrigger {
+            address: None,
+            event: "SaleStateChanged(uint256 indexed,bool)".into(),
+            values: Some(vec![EventValue {
+                index: 1,
+                r#type: ArgumentType::Bool,
+                value: Value::Boolean(true),
+            }]),
+        })
+        .unwrap();
+
+        let log = |active: bool| Log {
+            topics: vec![matcher.topic(), H256::from_low_u64_be(1)],
+            data: contracts::encode_args(&[Token::Bool(active)][..]).into(),
+            ..Default::default()
+        };
+        assert!(matcher.matches(&log(true)));
+        assert!(!matcher.matches(&log(false)));
+
+        // an indexed value before the values of the data
+        let matcher = EventMatcher::new(&EventTrigger {
+            address: None,
+            event: "SaleStateChanged(uint256 indexed,bool)".into(),
+            values: Some(vec![EventValue {
+                index: 0,
+                r#type: ArgumentType::Uint,
+                value: Value::Integer(2),
+            }]),
+        })
+        .unwrap();
+
+        let log = |phase: u64| Log {
+            topics: vec![matcher.topic(), H256::from_low_u64_be(phase)],
+            data: contracts::encode_args(&[Token::Bool(true)][..]).into(),
+            ..Default::default()
+        };
+        assert!(matcher.matches(&log(2)));
+        assert!(!matcher.matches(&log(1)));
+    }
+
+    #[test]
+    fn state_check_modes() {
+        assert!(combine(None, &[true, true]));
+        assert!(!combine(Some(&StateChecksMode::And), &[true, false]));
+        assert!(combine(Some(&StateChecksMode::Or), &[false, true]));
+        assert!(!combine(Some(&StateChecksMode::Or), &[false, false]));
+    }
+}
@@ -1,6 +1,6 @@
 use crate::token::Token;
 use serde::{Deserialize, Serialize};
-use std::time::Duration;
+use std::{collections::HashMap, time::Duration};
 use toml::Value;
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -49,13 +49,90 @@ pub struct Global {
     pub proxy_url: Option<String>,
     pub provider_url: String,
     pub flashbots_signer: Option<String>,
-    pub relays: Vec<String>,
+    pub relays: Vec<RelayEntry>,
+    pub simulation_relay: Option<String>,
+    pub simulation_mode: Option<SimulationMode>,
+    pub simulation_provider: Option<String>,
+    pub fee_strategies: Option<Vec<FeeStrategy>>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct FeeStrategy {
+    pub name: String,
+    pub base_fee_multiplier: f64, // of the predicted base fee, e.g. 2 for twice the base fee
+    pub priority_fee: Option<f64>, // in gwei
+    pub priority_fee_percentile: Option<f64>, // of recent priority fees, if no priority fee is set
+    pub maximum_fee: f64,         // in gwei
+    pub history_blocks: Option<u64>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub enum RelayAuth {
+    None,
+    Flashbots,
+    ApiKey,
+    Signature,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub enum RelaySignaturePayload {
+    BodyHash,
+    Body,
+}
+
+/// A relay, given either by its URL alone (with the default Flashbots auth)
+/// or as a table.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum RelayEntry {
+    Url(String),
+    Relay(Relay),
+}
+
+impl From<&RelayEntry> for Relay {
+    fn from(entry: &RelayEntry) -> Self {
+        match entry {
+            RelayEntry::Url(url) => Relay {
+                url: url.clone(),
+                auth: None,
+                auth_header: None,
+                api_key: None,
+                signature_payload: None,
+                headers: None,
+                timeout: None,
+                proxy_url: None,
+                methods: None,
+            },
+            RelayEntry::Relay(relay) => relay.clone(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Relay {
+    pub url: String,
+    pub auth: Option<RelayAuth>,
+    pub auth_header: Option<String>,
+    pub api_key: Option<String>,
+    pub signature_payload: Option<RelaySignaturePayload>,
+    pub headers: Option<HashMap<String, String>>,
+    pub timeout: Option<u64>, // in milliseconds
+    pub proxy_url: Option<String>,
+    pub methods: Option<Vec<String>>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub enum SimulationMode {
+    Single,
+    Compare,
+    Local,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
 pub enum MintMode {
     Flashbots,
     Normal,
+    Private,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
@@ -64,49 +141,131 @@ pub enum IncludeAddressType {
     From,
 }
 
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub enum StateCheckOperator {
+    Equal,
+    NotEqual,
+    Greater,
+    GreaterOrEqual,
+    Less,
+    LessOrEqual,
+    Between,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub enum StateChecksMode {
+    And,
+    Or,
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct StateChecks {
     pub address: Option<String>,
+    pub abi_path: Option<String>, // path to the ABI JSON of the contract, to call the function by name
     pub function: String,
     pub arguments: Vec<MintArgument>,
     pub return_value: Vec<MintArgument>,
+    pub operator: Option<StateCheckOperator>,
+    pub maximum_value: Option<Vec<MintArgument>>, // inclusive upper bound for Between
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct EventValue {
+    pub index: usize, // position of the value in the event signature
+    pub r#type: Token,
+    pub value: Value,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct EventTrigger {
+    pub address: Option<String>,
+    pub event: String, // e.g. "SaleStateChanged(bool)" or "Minted(address indexed,uint256)"
+    pub values: Option<Vec<EventValue>>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct PriceFunction {
     pub address: Option<String>,
+    pub abi_path: Option<String>, // path to the ABI JSON of the contract, to call the function by name
     pub function: String,
     pub arguments: Vec<MintArgument>,
     pub multiplier: u64,
 }
 
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BundleWindow {
+    pub blocks: Option<u64>,
+    pub validity: Option<u64>, // in seconds after the start time
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CoinbasePayment {
+    pub contract_address: Option<String>,
+    pub function: Option<String>,
+    pub value: Option<f64>, // in wei
+    pub share: Option<f64>, // share of the smart gas budget, between 0 and 1
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Mint {
     pub mode: MintMode,
     pub contract_address: String,
+    pub abi_path: Option<String>, // path to the ABI JSON of the contract, to call the function by name
     pub function: String,
     pub arguments: Vec<MintArgument>,
     pub value: f64,
     pub gas_fee: f64,
     pub priority_fee: Option<f64>,
+    pub fee_strategy: Option<String>,
     pub gas_limit: Option<u64>,
+    pub gas_margin: Option<f64>, // added to the simulated gas limit, e.g. 0.2 for 20%
     pub start_time: Option<u64>,
     pub transaction_count: Option<u64>,
     pub include_address_type: Option<IncludeAddressType>,
     pub include_address: Option<String>,
     pub include_method: Option<String>,
     pub state_checks: Option<Vec<StateChecks>>,
+    pub state_checks_mode: Option<StateChecksMode>,
+    pub trigger: Option<EventTrigger>,
     pub price_function: Option<PriceFunction>,
     pub script_identifier: Option<String>,
     pub initial_nonce: Option<u64>,
     pub bump_mempool: Option<bool>,
     pub extra_data: Option<String>,
+    pub bundle_window: Option<BundleWindow>,
+    pub private_tx_blocks: Option<u64>, // blocks a private tx may be included in, 25 by default
+    pub coinbase_payment: Option<CoinbasePayment>,
+    pub access_list: Option<bool>,
+    pub wallets: Option<Vec<String>>, // private keys, minting along with the account
+    pub wallets_file: Option<String>, // path to a JSON array of private keys
+    pub tracker: Option<Tracker>,
+    pub results_file: Option<String>, // received tokens are appended to it, results.jsonl by default
+    pub supply: Option<Supply>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Supply {
+    pub address: Option<String>,
+    pub total_supply: Option<String>,     // e.g. "totalSupply()"
+    pub max_supply: Option<String>,       // e.g. "maxSupply()"
+    pub wallet_limit: Option<String>,     // e.g. "maxPerWallet()"
+    pub wallet_minted: Option<String>,    // e.g. "numberMinted(address)", called with the wallet
+    pub quantity_argument: Option<usize>, // index of the quantity in the mint arguments
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Tracker {
+    pub blocks: u64,                 // blocks a tx may stay pending before a fee bump
+    pub fee_bump: Option<f64>,       // at least 0.1 for 10%
+    pub maximum_fee: f64,            // in gwei
+    pub cancel: Option<bool>,        // cancel txs still pending at the maximum fee
+    pub maximum_blocks: Option<u64>, // blocks txs are tracked for at most, 25 by default
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct MintArgument {
     pub r#type: Token,
-    pub value: Value,
+    pub value: Value, // mint arguments may use {address}, {index}, {nonce}, {block} and {timestamp}
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -122,6 +281,31 @@ pub enum SmartGas {
     Exclusive,
 }
 
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub enum OSSubmission {
+    Bundle,
+    Private,
+    MevShare,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub enum MevShareHint {
+    Calldata,
+    ContractAddress,
+    Logs,
+    FunctionSelector,
+    Hash,
+    TxHash,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MevShare {
+    pub hints: Option<Vec<MevShareHint>>,
+    pub builders: Option<Vec<String>>,
+    pub refund_percent: Option<u64>,
+    pub refund_address: Option<String>,
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct OpenSea {
     pub api: OSAPI,
@@ -129,10 +313,17 @@ pub struct OpenSea {
     pub api_delay: Option<u64>,
     pub smart_gas: SmartGas,
     pub estimate_gas: bool,
+    pub access_list: Option<bool>,
     pub gas_fee: f64,
     pub priority_fee: Option<f64>,
+    pub fee_strategy: Option<String>,
     pub gas_limit: u64,
     pub maximum_retry_attempts: usize,
+    pub bundle_window: Option<BundleWindow>,
+    pub submission: Option<OSSubmission>,
+    pub private_tx_blocks: Option<u64>, // blocks a private tx may be included in, 25 by default
+    pub mev_share: Option<MevShare>,
+    pub coinbase_payment: Option<CoinbasePayment>,
     pub drop: Option<OSDrop>,
     pub limit: Option<OSLimit>,
 }
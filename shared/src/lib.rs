@@ -1,5 +1,7 @@
 use std::error::Error as StdError;
 
+pub mod abi;
+pub mod checks;
 pub mod config;
 pub mod contracts;
 pub mod token;
@@ -9,6 +9,84 @@ use toml::Value;
 
 use crate::util;
 
+/// The values of the placeholders of mint arguments, which are only known
+/// when a tx is signed.
+#[derive(Clone, Debug, Default)]
+pub struct Placeholders {
+    /// `{address}`, the wallet sending the tx.
+    pub address: Address,
+    /// `{index}`, the index of the tx among the txs of the wallet.
+    pub index: u64,
+    /// `{nonce}`, the nonce of the tx.
+    pub nonce: U256,
+    /// `{block}`, the block the tx is sent for. Flashbots mints sign their
+    /// txs once for the first block of the bundle window, so the txs keep
+    /// that block when the bundle targets the blocks after it.
+    pub block: u64,
+    /// `{timestamp}`, the timestamp the tx is sent for, in seconds.
+    pub timestamp: u64,
+}
+
+impl Placeholders {
+    /// Whether any of the arguments has a placeholder in its value.
+    pub fn any(arguments: &[MintArgument]) -> bool {
+        fn has_placeholder(value: &Value) -> bool {
+            match value {
+                Value::String(s) => PLACEHOLDERS.iter().any(|p| s.contains(p)),
+                Value::Array(values) => values.iter().any(has_placeholder),
+                Value::Table(table) => table.values().any(has_placeholder),
+                _ => false,
+            }
+        }
+        arguments.iter().any(|x| has_placeholder(&x.value))
+    }
+
+    /// The arguments, with the placeholders in their values resolved.
+    pub fn arguments(&self, arguments: &[MintArgument]) -> Vec<MintArgument> {
+        arguments
+            .iter()
+            .map(|x| MintArgument {
+                r#type: x.r#type.clone(),
+                value: self.resolve(&x.value),
+            })
+            .collect()
+    }
+
+    /// Replaces the placeholders in a value.
+    ///
+    /// A string that is a single placeholder is replaced by its value (an
+    /// integer for numbers), others get the placeholders they contain
+    /// replaced by their text.
+    pub fn resolve(&self, value: &Value) -> Value {
+        match value {
+            Value::String(s) => match s.as_str() {
+                "{address}" => Value::String(format!("0x{:x}", self.address)),
+                "{index}" => Value::Integer(self.index as i64),
+                "{nonce}" => Value::Integer(self.nonce.as_u64() as i64),
+                "{block}" => Value::Integer(self.block as i64),
+                "{timestamp}" => Value::Integer(self.timestamp as i64),
+                _ => Value::String(
+                    s.replace("{address}", &format!("0x{:x}", self.address))
+                        .replace("{index}", &self.index.to_string())
+                        .replace("{nonce}", &self.nonce.to_string())
+                        .replace("{block}", &self.block.to_string())
+                        .replace("{timestamp}", &self.timestamp.to_string()),
+                ),
+            },
+            Value::Array(values) => Value::Array(values.iter().map(|v| self.resolve(v)).collect()),
+            Value::Table(table) => Value::Table(
+                table
+                    .iter()
+                    .map(|(key, value)| (key.clone(), self.resolve(value)))
+                    .collect(),
+            ),
+            value => value.clone(),
+        }
+    }
+}
+
+const PLACEHOLDERS: [&str; 5] = ["{address}", "{index}", "{nonce}", "{block}", "{timestamp}"];
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum Token {
     /// Address.
@@ -67,7 +145,7 @@ impl Token {
             Self::Uint => match value {
                 Value::Integer(i) if *i >= 0 => U256::from(*i).into_token(),
                 Value::Float(i) if *i >= 0. => U256::from(*i as u128).into_token(),
-                Value::String(s) => U256::from_str(s)?.into_token(),
+                Value::String(s) => parse_uint(s)?.into_token(),
                 _ => unimplemented!(),
             },
             Self::String => match value {
@@ -118,3 +196,50 @@ impl Token {
         })
     }
 }
+
+/// Parses a uint given as a string, which is hex with or without a `0x`
+/// prefix.
+pub(crate) fn parse_uint(s: &str) -> Result<U256, crate::Error> {
+    Ok(U256::from_str(s.strip_prefix("0x").unwrap_or(s))?)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn resolve_placeholders() {
+        let placeholders = Placeholders {
+            address: Address::from_low_u64_be(0xdead),
+            index: 1,
+            nonce: 7.into(),
+            block: 15_000_000,
+            timestamp: 1_650_000_000,
+        };
+        let argument = |r#type: Token, value: &str| MintArgument {
+            r#type,
+            value: Value::String(value.into()),
+        };
+        let arguments = vec![
+            argument(Token::Address, "{address}"),
+            argument(Token::Uint, "{block}"),
+            argument(Token::String, "{index}-{nonce}"),
+        ];
+        assert!(Placeholders::any(&arguments));
+        assert!(!Placeholders::any(&[argument(Token::Uint, "15")]));
+
+        let tokens = placeholders
+            .arguments(&arguments)
+            .iter()
+            .map(|x| x.r#type.to_token(&x.value).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            tokens,
+            vec![
+                AbiToken::Address(placeholders.address),
+                AbiToken::Uint(15_000_000.into()),
+                AbiToken::String("1-7".into()),
+            ]
+        );
+    }
+}